@@ -0,0 +1,4 @@
+ALTER TABLE sources
+DROP COLUMN method;
+
+DROP TYPE METHOD;
\ No newline at end of file
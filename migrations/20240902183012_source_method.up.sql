@@ -0,0 +1,4 @@
+CREATE TYPE METHOD AS ENUM('get', 'post', 'put', 'patch', 'delete', 'head', 'options');
+
+ALTER TABLE sources
+ADD COLUMN method METHOD NOT NULL DEFAULT 'get';
\ No newline at end of file
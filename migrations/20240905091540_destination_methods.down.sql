@@ -0,0 +1,23 @@
+ALTER TABLE destinations
+DROP COLUMN methods;
+
+UPDATE sources
+SET body_type = 'none'
+WHERE body_type = 'passthrough';
+
+ALTER TABLE sources DROP CONSTRAINT ck_body;
+ALTER TABLE sources ALTER COLUMN body_type DROP DEFAULT;
+ALTER TYPE BODY RENAME TO BODY_OLD;
+
+CREATE TYPE BODY AS ENUM('none', 'text', 'json', 'form', 'multi');
+
+ALTER TABLE sources ALTER COLUMN body_type TYPE BODY USING body_type::TEXT::BODY;
+ALTER TABLE sources ALTER COLUMN body_type SET DEFAULT 'none';
+ALTER TABLE sources ADD CONSTRAINT ck_body CHECK (
+  (body_type = 'text') = (body_text IS NOT NULL)
+  AND (body_type = 'json') = (body_json IS NOT NULL)
+  AND (body_type = 'form') = (body_form IS NOT NULL)
+  AND (body_type = 'multi') = (body_multi IS NOT NULL)
+);
+
+DROP TYPE BODY_OLD;
\ No newline at end of file
@@ -0,0 +1,4 @@
+ALTER TYPE BODY ADD VALUE 'passthrough';
+
+ALTER TABLE destinations
+ADD COLUMN methods METHOD[] NOT NULL DEFAULT '{get}';
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE sources
+ADD COLUMN forward JSON NOT NULL DEFAULT '{"query": {}, "headers": {}}';
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE sources
+DROP COLUMN circuit_breaker;
\ No newline at end of file
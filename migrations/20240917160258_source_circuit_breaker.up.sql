@@ -0,0 +1,2 @@
+ALTER TABLE sources
+ADD COLUMN circuit_breaker JSON NULL;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE sources
+DROP COLUMN cache;
\ No newline at end of file
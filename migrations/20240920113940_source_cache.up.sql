@@ -0,0 +1,2 @@
+ALTER TABLE sources
+ADD COLUMN cache JSON NULL;
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE config_versions
+DROP COLUMN rollback_of,
+DROP COLUMN config;
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE config_versions
+ADD COLUMN config JSON NULL,
+ADD COLUMN rollback_of UUID NULL REFERENCES config_versions(id);
\ No newline at end of file
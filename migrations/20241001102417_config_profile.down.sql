@@ -0,0 +1,2 @@
+ALTER TABLE config_versions
+DROP COLUMN profile;
\ No newline at end of file
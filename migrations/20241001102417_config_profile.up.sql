@@ -0,0 +1,2 @@
+ALTER TABLE config_versions
+ADD COLUMN profile VARCHAR NULL;
\ No newline at end of file
@@ -0,0 +1,35 @@
+CREATE FUNCTION json_multimap_to_hashmap(json_input JSON)
+  RETURNS JSON
+  LANGUAGE sql
+AS $$
+  SELECT coalesce(json_object_agg(pairs.key, CASE json_typeof(pairs.value)
+    WHEN 'array' THEN (
+      SELECT to_json(coalesce(string_agg(elements.value, ','), ''))
+      FROM json_array_elements_text(pairs.value) AS elements
+    )
+    ELSE pairs.value
+  END), '{}')
+  FROM json_each(json_input) AS pairs;
+$$;
+
+ALTER TABLE destinations
+ALTER COLUMN headers DROP DEFAULT,
+ALTER COLUMN headers TYPE STRING_HASHMAP USING json_multimap_to_hashmap(headers),
+ALTER COLUMN headers SET DEFAULT '{}';
+
+ALTER TABLE sources
+DROP COLUMN list_format,
+ALTER COLUMN headers DROP DEFAULT,
+ALTER COLUMN headers TYPE STRING_HASHMAP USING json_multimap_to_hashmap(headers),
+ALTER COLUMN headers SET DEFAULT '{}',
+ALTER COLUMN params DROP DEFAULT,
+ALTER COLUMN params TYPE STRING_HASHMAP USING json_multimap_to_hashmap(params),
+ALTER COLUMN params SET DEFAULT '{}';
+
+DROP FUNCTION json_multimap_to_hashmap;
+
+DROP TYPE LIST_FORMAT;
+
+DROP DOMAIN STRING_MULTIMAP;
+
+DROP FUNCTION json_is_multimap;
\ No newline at end of file
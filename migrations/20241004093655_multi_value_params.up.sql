@@ -0,0 +1,43 @@
+CREATE FUNCTION json_is_multimap(json_input JSON)
+  RETURNS BOOLEAN
+  LANGUAGE plpgsql
+AS $$
+DECLARE
+  is_multimap BOOLEAN;
+BEGIN
+  IF json_typeof(json_input) != 'object' THEN
+    RETURN FALSE;
+  END IF;
+
+  SELECT coalesce(EVERY(
+    json_typeof(pairs.value) = 'string'
+    OR (json_typeof(pairs.value) = 'array' AND NOT EXISTS(
+      SELECT *
+      FROM json_array_elements(pairs.value) AS elements
+      WHERE json_typeof(elements.value) != 'string'
+    ))
+  ), TRUE) OR json_input IS NULL
+  INTO is_multimap
+  FROM json_each(json_input) AS pairs;
+
+  RETURN is_multimap;
+END; $$;
+
+CREATE DOMAIN STRING_MULTIMAP AS JSON
+CHECK (json_is_multimap(value));
+
+CREATE TYPE LIST_FORMAT AS ENUM('repeat', 'comma');
+
+ALTER TABLE sources
+ALTER COLUMN params DROP DEFAULT,
+ALTER COLUMN params TYPE STRING_MULTIMAP USING params::JSON,
+ALTER COLUMN params SET DEFAULT '{}',
+ALTER COLUMN headers DROP DEFAULT,
+ALTER COLUMN headers TYPE STRING_MULTIMAP USING headers::JSON,
+ALTER COLUMN headers SET DEFAULT '{}',
+ADD COLUMN list_format LIST_FORMAT NOT NULL DEFAULT 'repeat';
+
+ALTER TABLE destinations
+ALTER COLUMN headers DROP DEFAULT,
+ALTER COLUMN headers TYPE STRING_MULTIMAP USING headers::JSON,
+ALTER COLUMN headers SET DEFAULT '{}';
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE destinations
+ADD COLUMN deadline INTERVAL NULL;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE destinations__sources
+DROP COLUMN required;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE destinations__sources
+ADD COLUMN required BOOLEAN NOT NULL DEFAULT TRUE;
\ No newline at end of file
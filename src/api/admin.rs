@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{extract::{Path, State}, routing::{delete, get, post}, Router};
+use http::HeaderMap;
+use serde_json::json;
+
+use crate::{config::ConfigVersion, data::get_conn};
+
+use super::{cache::DestinationCaches, circuits::Circuits, response::Response, Error, RoutingTable};
+
+#[derive(Clone)]
+struct AdminState {
+  token: Arc<String>,
+}
+
+impl AdminState {
+  fn authorize(&self, headers: &HeaderMap) -> Result<(), Error> {
+    let value = headers.get("Authorization").map(|value| value.to_str()).transpose()?;
+
+    match value.and_then(|value| value.strip_prefix("Bearer ")) {
+      Some(value) if constant_time_eq(value.as_bytes(), self.token.as_bytes()) => Ok(()),
+      _ => Err(Error::Unauthorized),
+    }
+  }
+}
+
+/// Compares without stopping at the first differing byte, so response timing does not leak the token.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+  left.len() == right.len() && left.iter().zip(right).fold(0, |diff, (l, r)| diff | (l ^ r)) == 0
+}
+
+/// Admin routes, every one of them requiring `token` as a bearer token.
+pub fn router(token: String) -> Router {
+  Router::new()
+    .route("/circuits", get(circuits))
+    .route("/cache/destinations/:code", delete(purge_destination_cache))
+    .route("/config/versions", get(config_versions))
+    .route("/config/versions/:from/diff/:to", get(config_diff))
+    .route("/config/versions/:version/rollback", post(config_rollback))
+    .with_state(AdminState { token: Arc::new(token) })
+}
+
+async fn circuits(State(state): State<AdminState>, headers: HeaderMap) -> Result<Response, Error> {
+  state.authorize(&headers)?;
+
+  Ok(Response::JsonString(serde_json::to_string(&Circuits::snapshot())?))
+}
+
+async fn purge_destination_cache(State(state): State<AdminState>, headers: HeaderMap, Path(code): Path<String>) -> Result<Response, Error> {
+  state.authorize(&headers)?;
+
+  let purged = DestinationCaches::purge(&code);
+  println!("Purged {} cached results for destination: ({})", purged, &code);
+
+  Ok(Response::JsonString(json!({ "destination": code, "purged": purged }).to_string()))
+}
+
+async fn config_versions(State(state): State<AdminState>, headers: HeaderMap) -> Result<Response, Error> {
+  state.authorize(&headers)?;
+
+  Ok(Response::JsonString(serde_json::to_string(&ConfigVersion::select_all(&mut get_conn().await?).await?)?))
+}
+
+async fn config_diff(State(state): State<AdminState>, headers: HeaderMap, Path((from, to)): Path<(String, String)>) -> Result<Response, Error> {
+  state.authorize(&headers)?;
+
+  let mut conn = get_conn().await?;
+  let plan = ConfigVersion::select_by_prefix(&from, &mut conn).await?
+    .diff(&ConfigVersion::select_by_prefix(&to, &mut conn).await?)?;
+
+  Ok(Response::JsonString(serde_json::to_string(&plan)?))
+}
+
+async fn config_rollback(State(state): State<AdminState>, headers: HeaderMap, Path(version): Path<String>) -> Result<Response, Error> {
+  state.authorize(&headers)?;
+
+  let version = ConfigVersion::select_by_prefix(&version, &mut get_conn().await?).await?;
+  version.rollback().await?;
+  RoutingTable::reload().await?;
+
+  Ok(Response::JsonString(json!({ "rollback_of": version.id }).to_string()))
+}
\ No newline at end of file
@@ -0,0 +1,264 @@
+use std::{
+  collections::{BTreeSet, HashMap},
+  sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+  time::{Duration, Instant}
+};
+
+use http::Method as HttpMethod;
+use reqwest::Request;
+use serde_json::Value;
+
+use crate::{data::types::Cache, utils::Hasher};
+use super::{context::RequestContext, routing::RouteSource, template::Template};
+
+static SOURCE_CACHES: OnceLock<Mutex<HashMap<String, CacheStore>>> = OnceLock::new();
+static DESTINATION_CACHES: OnceLock<Mutex<HashMap<String, CacheStore>>> = OnceLock::new();
+
+pub enum CacheLookup {
+  Fresh(Value),
+  /// Expired but within `stale_while_revalidate`. The flag is set for the single
+  /// caller that claimed the background refresh.
+  Revalidate(Value, bool),
+  /// Expired and only to be served if the upstream fails, per `stale_if_error`.
+  Stale(Value),
+  Miss,
+}
+
+struct CacheEntry {
+  value: Value,
+  size: usize,
+  expires_at: Instant,
+  stale_until: Instant,
+  used_at: Instant,
+  refreshing: bool,
+}
+
+#[derive(Default)]
+struct CacheStore {
+  entries: HashMap<Vec<u8>, CacheEntry>,
+  bytes: usize,
+}
+
+impl CacheStore {
+  fn lookup(&mut self, key: &[u8], cache: &Cache) -> CacheLookup {
+    let Some(entry) = self.entries.get_mut(key) else {
+      return CacheLookup::Miss;
+    };
+    let now = Instant::now();
+
+    if entry.expires_at > now {
+      entry.used_at = now;
+      return CacheLookup::Fresh(entry.value.clone());
+    }
+
+    let expired_for = now - entry.expires_at;
+
+    if cache.stale_while_revalidate.is_some_and(|window| expired_for < window) {
+      entry.used_at = now;
+      let claimed = !entry.refreshing;
+      entry.refreshing = true;
+      return CacheLookup::Revalidate(entry.value.clone(), claimed);
+    }
+
+    if cache.stale_if_error.is_some_and(|window| expired_for < window) {
+      return CacheLookup::Stale(entry.value.clone());
+    }
+
+    CacheLookup::Miss
+  }
+
+  fn insert(&mut self, key: Vec<u8>, value: Value, ttl: Duration, cache: &Cache) {
+    let size = value.to_string().len();
+    if cache.max_bytes.is_some_and(|max_bytes| size > max_bytes) {
+      self.remove(&key);
+      return;
+    }
+
+    let now = Instant::now();
+    self.remove(&key);
+    self.entries.insert(key, CacheEntry {
+      value,
+      size,
+      expires_at: now + ttl,
+      stale_until: now + ttl + cache.stale_window(),
+      used_at: now,
+      refreshing: false,
+    });
+    self.bytes += size;
+    self.evict(cache, now);
+  }
+
+  fn release(&mut self, key: &[u8]) {
+    if let Some(entry) = self.entries.get_mut(key) {
+      entry.refreshing = false;
+    }
+  }
+
+  fn remove(&mut self, key: &[u8]) {
+    if let Some(entry) = self.entries.remove(key) {
+      self.bytes -= entry.size;
+    }
+  }
+
+  /// Drops entries past their stale window, then the least recently used ones until the limits hold.
+  fn evict(&mut self, cache: &Cache, now: Instant) {
+    let expired = self.entries.iter()
+      .filter(|(_, entry)| entry.stale_until <= now)
+      .map(|(key, _)| key.clone())
+      .collect::<Vec<_>>();
+    for key in expired {
+      self.remove(&key);
+    }
+
+    while self.entries.len() > cache.max_entries || cache.max_bytes.is_some_and(|max_bytes| self.bytes > max_bytes) {
+      let Some(key) = self.entries.iter()
+        .min_by_key(|(_, entry)| entry.used_at)
+        .map(|(key, _)| key.clone()) else {
+        break;
+      };
+      self.remove(&key);
+    }
+  }
+}
+
+/// Process-wide response caches, one store per source code.
+pub struct Caches;
+
+impl Caches {
+  fn lock() -> MutexGuard<'static, HashMap<String, CacheStore>> {
+    SOURCE_CACHES.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Key over the fully resolved request, or `None` when the body cannot be read back.
+  pub fn key(request: &Request) -> Option<Vec<u8>> {
+    let mut headers = request.headers().iter()
+      .map(|(name, value)| format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes())))
+      .collect::<Vec<_>>();
+    headers.sort();
+
+    let body = match request.body() {
+      Some(body) => String::from_utf8_lossy(body.as_bytes()?).into_owned(),
+      None => String::new(),
+    };
+
+    Some(Hasher::hash_string(format!("{}\n{}\n{}\n{}", request.method(), request.url(), headers.join("\n"), body)))
+  }
+
+  pub fn lookup(code: &str, cache: &Cache, key: &[u8]) -> CacheLookup {
+    match Self::lock().get_mut(code) {
+      Some(store) => store.lookup(key, cache),
+      None => CacheLookup::Miss,
+    }
+  }
+
+  pub fn insert(code: &str, cache: &Cache, key: Vec<u8>, value: Value, ttl: Duration) {
+    Self::lock().entry(code.to_owned()).or_default().insert(key, value, ttl, cache);
+  }
+
+  /// Gives up a claimed background refresh so that a later request can retry it.
+  pub fn release(code: &str, key: &[u8]) {
+    if let Some(store) = Self::lock().get_mut(code) {
+      store.release(key);
+    }
+  }
+}
+
+/// Process-wide caches of filtered destination output, one store per destination code.
+pub struct DestinationCaches;
+
+impl DestinationCaches {
+  fn lock() -> MutexGuard<'static, HashMap<String, CacheStore>> {
+    DESTINATION_CACHES.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Inbound headers the sources can see: the caller's token and any header they forward or reference in templates.
+  pub fn header_names(sources: &[RouteSource]) -> BTreeSet<String> {
+    let mut names = BTreeSet::from([String::from("authorization")]);
+    for RouteSource { source, .. } in sources {
+      names.extend(source.forward.headers.keys().cloned());
+      names.extend(Template::header_names(source));
+    }
+    names
+  }
+
+  /// Key over everything the sources can see of the inbound request: path, query, body
+  /// and the headers listed in `header_names`.
+  pub fn key(method: &HttpMethod, path: &str, context: &RequestContext, header_names: &BTreeSet<String>) -> Vec<u8> {
+    let headers = header_names.iter()
+      .flat_map(|name| context.headers.get_all(name.as_str()).iter()
+        .map(move |value| format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes()))))
+      .collect::<Vec<_>>();
+
+    let mut query = context.query.iter()
+      .map(|(key, value)| format!("{}={}", key, value))
+      .collect::<Vec<_>>();
+    query.sort();
+
+    let body = context.body.as_ref().map(Value::to_string).unwrap_or_default();
+
+    Hasher::hash_string(format!("{}\n{}\n{}\n{}\n{}", method, path, query.join("&"), headers.join("\n"), body))
+  }
+
+  pub fn get(code: &str, cache: &Cache, key: &[u8]) -> Option<String> {
+    match Self::lock().get_mut(code)?.lookup(key, cache) {
+      CacheLookup::Fresh(Value::String(result)) => Some(result),
+      _ => None,
+    }
+  }
+
+  pub fn insert(code: &str, cache: &Cache, key: Vec<u8>, result: String) {
+    Self::lock().entry(code.to_owned()).or_default().insert(key, Value::String(result), cache.ttl, cache);
+  }
+
+  /// Drops every cached result, since they were produced by a routing table that has been replaced.
+  pub fn clear() {
+    Self::lock().clear();
+  }
+
+  /// Drops every cached result of a destination, returning how many there were.
+  pub fn purge(code: &str) -> usize {
+    Self::lock().remove(code).map(|store| store.entries.len()).unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn cache(max_bytes: Option<usize>) -> Cache {
+    Cache {
+      ttl: Duration::ZERO,
+      max_entries: 10,
+      max_bytes,
+      cache_control: false,
+      stale_while_revalidate: Some(Duration::from_secs(60)),
+      stale_if_error: None,
+    }
+  }
+
+  #[test]
+  fn claims_a_stale_entry_once_until_released() {
+    let cache = cache(None);
+    let mut store = CacheStore::default();
+    store.insert(b"key".to_vec(), json!("value"), Duration::ZERO, &cache);
+
+    assert!(matches!(store.lookup(b"key", &cache), CacheLookup::Revalidate(_, true)));
+    assert!(matches!(store.lookup(b"key", &cache), CacheLookup::Revalidate(_, false)));
+
+    store.release(b"key");
+    assert!(matches!(store.lookup(b"key", &cache), CacheLookup::Revalidate(_, true)));
+  }
+
+  #[test]
+  fn oversize_insert_drops_the_previous_entry() {
+    let cache = cache(Some(8));
+    let mut store = CacheStore::default();
+    store.insert(b"key".to_vec(), json!("small"), Duration::ZERO, &cache);
+    store.insert(b"key".to_vec(), json!("much too large"), Duration::ZERO, &cache);
+
+    assert!(matches!(store.lookup(b"key", &cache), CacheLookup::Miss));
+    assert_eq!(store.bytes, 0);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,125 @@
+use std::{
+  collections::HashMap,
+  sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+  time::Instant
+};
+
+use serde::Serialize;
+
+use crate::data::types::CircuitBreaker;
+
+static CIRCUITS: OnceLock<Mutex<HashMap<String, Circuit>>> = OnceLock::new();
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+  Closed,
+  Open,
+  HalfOpen,
+}
+
+struct Circuit {
+  state: CircuitState,
+  changed_at: Instant,
+  failures: u32,
+  probes: u32,
+  successes: u32,
+}
+
+impl Circuit {
+  fn new() -> Self {
+    Self {
+      state: CircuitState::Closed,
+      changed_at: Instant::now(),
+      failures: 0,
+      probes: 0,
+      successes: 0,
+    }
+  }
+
+  fn transition(&mut self, state: CircuitState) {
+    self.state = state;
+    self.changed_at = Instant::now();
+    self.failures = 0;
+    self.probes = 0;
+    self.successes = 0;
+  }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CircuitStatus {
+  pub state: CircuitState,
+  pub failures: u32,
+  pub since_ms: u128,
+}
+
+/// Process-wide circuit breakers keyed by source code.
+pub struct Circuits;
+
+impl Circuits {
+  fn lock() -> MutexGuard<'static, HashMap<String, Circuit>> {
+    CIRCUITS.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Returns `false` when the request must be short-circuited. A half-open circuit
+  /// admits up to `half_open_probes` requests, or more if those probes never reported back.
+  pub fn acquire(code: &str, breaker: &CircuitBreaker) -> bool {
+    let mut circuits = Self::lock();
+    let circuit = circuits.entry(code.to_owned()).or_insert_with(Circuit::new);
+
+    if circuit.state == CircuitState::Open && circuit.changed_at.elapsed() >= breaker.open_duration {
+      circuit.transition(CircuitState::HalfOpen);
+    }
+
+    match circuit.state {
+      CircuitState::Closed => true,
+      CircuitState::Open => false,
+      CircuitState::HalfOpen => {
+        if circuit.probes < breaker.half_open_probes || circuit.changed_at.elapsed() >= breaker.open_duration {
+          circuit.probes += 1;
+          true
+        } else {
+          false
+        }
+      },
+    }
+  }
+
+  pub fn record(code: &str, breaker: &CircuitBreaker, success: bool) {
+    let mut circuits = Self::lock();
+    let circuit = circuits.entry(code.to_owned()).or_insert_with(Circuit::new);
+
+    match (circuit.state, success) {
+      (CircuitState::Closed, true) => circuit.failures = 0,
+      (CircuitState::Closed, false) => {
+        circuit.failures += 1;
+        if circuit.failures >= breaker.failure_threshold {
+          println!("Circuit opened for source: ({})", code);
+          circuit.transition(CircuitState::Open);
+        }
+      },
+      (CircuitState::HalfOpen, true) => {
+        circuit.successes += 1;
+        if circuit.successes >= breaker.half_open_probes {
+          println!("Circuit closed for source: ({})", code);
+          circuit.transition(CircuitState::Closed);
+        }
+      },
+      (CircuitState::HalfOpen, false) => {
+        println!("Circuit reopened for source: ({})", code);
+        circuit.transition(CircuitState::Open);
+      },
+      (CircuitState::Open, _) => (),
+    }
+  }
+
+  pub fn snapshot() -> HashMap<String, CircuitStatus> {
+    Self::lock().iter()
+      .map(|(code, circuit)| (code.to_owned(), CircuitStatus {
+        state: circuit.state,
+        failures: circuit.failures,
+        since_ms: circuit.changed_at.elapsed().as_millis(),
+      }))
+      .collect()
+  }
+}
\ No newline at end of file
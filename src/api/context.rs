@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use axum::extract::Query;
+use http::{request::Parts, HeaderMap, HeaderName};
+use serde_json::Value;
+
+use crate::data::types::Forward;
+use super::Error;
+
+pub struct RequestContext {
+  pub body: Option<Value>,
+  pub query: Vec<(String, String)>,
+  pub headers: HeaderMap,
+  pub variables: HashMap<String, String>,
+}
+
+impl RequestContext {
+  pub fn new(parts: &Parts, body: Option<Value>, path_params: HashMap<String, String>) -> Result<Self, Error> {
+    let query = Query::<Vec<(String, String)>>::try_from_uri(&parts.uri)
+      .map_err(|error| Error::BadRequest(error.body_text()))?.0;
+
+    let mut variables = HashMap::new();
+    for (key, value) in parts.headers.iter() {
+      if let Ok(value) = value.to_str() {
+        variables.entry(format!("header.{}", key)).or_insert_with(|| value.to_owned());
+      }
+    }
+    for (key, value) in query.iter() {
+      variables.entry(format!("query.{}", key)).or_insert_with(|| value.to_owned());
+    }
+    for (key, value) in path_params {
+      variables.insert(format!("path.{}", key), value);
+    }
+
+    Ok(Self {
+      body,
+      query,
+      headers: parts.headers.clone(),
+      variables,
+    })
+  }
+
+  pub fn forwarded_query(&self, forward: &Forward) -> Vec<(String, String)> {
+    self.query.iter()
+      .filter_map(|(key, value)| forward.query.get(key)
+        .map(|name| (name.to_owned(), value.to_owned())))
+      .collect()
+  }
+
+  pub fn forwarded_headers(&self, forward: &Forward) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    for (key, value) in self.headers.iter() {
+      if let Some(name) = forward.headers.get(key.as_str()) {
+        headers.append(HeaderName::try_from(name)?, value.clone());
+      }
+    }
+
+    Ok(headers)
+  }
+}
\ No newline at end of file
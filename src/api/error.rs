@@ -1,8 +1,9 @@
 use std::time::SystemTimeError;
 
+use crate::config::Error as ConfigError;
 use crate::data::Error as DataError;
-use axum::response::IntoResponse;
-use http::header::ToStrError;
+use axum::{response::IntoResponse, Error as AxumError};
+use http::header::{InvalidHeaderName, InvalidHeaderValue, ToStrError};
 use sqlx::Error as SqlxError;
 use reqwest::{Error as ReqwestError, StatusCode};
 use jq_rs::Error as JqError;
@@ -10,22 +11,31 @@ use serde_json::Error as JsonError;
 use tokio::task::JoinError;
 use regex::Error as RegexError;
 use http::Error as HttpError;
+use http_body_util::LengthLimitError;
 
 #[derive(Debug)]
 pub enum Error {
   NotFound,
+  MethodNotAllowed,
   BadRequest(String),
   Unauthorized,
+  PayloadTooLarge,
   InternalServerError(String),
+  ServiceUnavailable(String),
+  GatewayTimeout(String),
 }
 
 impl IntoResponse for Error {
   fn into_response(self) -> axum::response::Response {
     match self {
       Self::NotFound => (StatusCode::NOT_FOUND, String::new()),
+      Self::MethodNotAllowed => (StatusCode::METHOD_NOT_ALLOWED, String::new()),
       Self::BadRequest(err) => (StatusCode::BAD_REQUEST, err),
       Self::Unauthorized => (StatusCode::UNAUTHORIZED, String::new()),
+      Self::PayloadTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, String::new()),
       Self::InternalServerError(err) => (StatusCode::INTERNAL_SERVER_ERROR, err),
+      Self::ServiceUnavailable(err) => (StatusCode::SERVICE_UNAVAILABLE, err),
+      Self::GatewayTimeout(err) => (StatusCode::GATEWAY_TIMEOUT, err),
     }.into_response()
   }
 }
@@ -39,6 +49,15 @@ impl From<DataError> for Error {
   }
 }
 
+impl From<ConfigError> for Error {
+  fn from(value: ConfigError) -> Self {
+    match value {
+      ConfigError::Database(error) => Self::from(error),
+      _ => Self::InternalServerError(value.to_string()),
+    }
+  }
+}
+
 impl From<SqlxError> for Error {
   fn from(value: SqlxError) -> Self {
     Self::from(DataError::from(value))
@@ -69,6 +88,18 @@ impl From<ToStrError> for Error {
   }
 }
 
+impl From<InvalidHeaderName> for Error {
+  fn from(value: InvalidHeaderName) -> Self {
+    Self::InternalServerError(value.to_string())
+  }
+}
+
+impl From<InvalidHeaderValue> for Error {
+  fn from(value: InvalidHeaderValue) -> Self {
+    Self::InternalServerError(value.to_string())
+  }
+}
+
 impl From<JoinError> for Error {
   fn from(value: JoinError) -> Self {
     Self::InternalServerError(value.to_string())
@@ -87,6 +118,15 @@ impl From<RegexError> for Error {
   }
 }
 
+impl From<AxumError> for Error {
+  fn from(value: AxumError) -> Self {
+    match value.into_inner().downcast::<LengthLimitError>() {
+      Ok(_) => Self::PayloadTooLarge,
+      Err(error) => Self::BadRequest(error.to_string()),
+    }
+  }
+}
+
 impl From<HttpError> for Error {
   fn from(value: HttpError) -> Self {
     Self::InternalServerError(value.to_string())
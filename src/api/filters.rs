@@ -0,0 +1,32 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use jq_rs::JqProgram;
+
+use super::Error;
+
+thread_local! {
+  /// Compiled programs are not `Send`, so each worker thread keeps its own, reset when routing is reloaded.
+  static PROGRAMS: RefCell<(u64, HashMap<String, JqProgram>)> = RefCell::new((0, HashMap::new()));
+}
+
+pub struct Filters;
+
+impl Filters {
+  /// Runs `filter` over `input`, compiling it once per thread for each routing `generation`.
+  pub fn run(generation: u64, filter: &str, input: &str) -> Result<String, Error> {
+    PROGRAMS.with(|programs| {
+      let mut programs = programs.borrow_mut();
+
+      if programs.0 != generation {
+        *programs = (generation, HashMap::new());
+      }
+
+      let program = match programs.1.get_mut(filter) {
+        Some(program) => program,
+        None => programs.1.entry(filter.to_owned()).or_insert(jq_rs::compile(filter)?),
+      };
+
+      Ok(program.run(input)?.trim().to_string())
+    })
+  }
+}
\ No newline at end of file
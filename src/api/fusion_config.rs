@@ -2,10 +2,16 @@ use std::{env, net::Ipv4Addr, str::FromStr};
 
 use regex::Regex;
 
+/// Largest inbound request body accepted when `API_BODY_LIMIT` is not set.
+const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
 pub struct FusionConfig {
   pub address: Ipv4Addr,
   pub port: u16,
   pub path: String,
+  pub admin_path: Option<String>,
+  pub admin_token: Option<String>,
+  pub body_limit: usize,
 }
 
 impl FusionConfig {
@@ -13,6 +19,11 @@ impl FusionConfig {
     let address = env::var("API_BIND_ADDRESS").unwrap();
     let port = env::var("API_BIND_PORT").unwrap().parse::<u16>().unwrap();
     let path = env::var("API_BIND_PATH").unwrap();
+    let admin_path = env::var("API_ADMIN_PATH").ok();
+    let admin_token = env::var("API_ADMIN_TOKEN").ok().filter(|token| !token.is_empty());
+    let body_limit = env::var("API_BODY_LIMIT").ok()
+      .map(|limit| limit.parse::<usize>().unwrap())
+      .unwrap_or(DEFAULT_BODY_LIMIT);
     
     assert!(Regex::new(r"^((25[0-5]|(2[0-4]|1\d|[1-9]|)\d)\.?\b){4}$").unwrap().is_match(&address));
 
@@ -20,10 +31,13 @@ impl FusionConfig {
       address: Ipv4Addr::from_str(&address).unwrap(),
       port,
       path,
+      admin_path,
+      admin_token,
+      body_limit,
     }
   }
 
   pub fn full_address(&self) -> String {
-    format!("{}:{}", self.address.to_string(), self.port.to_string())
+    format!("{}:{}", self.address, self.port)
   }
 }
\ No newline at end of file
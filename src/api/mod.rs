@@ -1,134 +1,397 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{collections::HashMap, sync::Arc, time::{Duration, SystemTime}};
 
-use axum::extract::{FromRequestParts, Path, Request};
-use http::{HeaderValue, StatusCode};
-use reqwest::{header::HeaderMap, multipart, Client};
+use axum::{body::to_bytes, extract::{FromRequestParts, Path, Request}};
+use http::{HeaderName, HeaderValue, StatusCode};
+use reqwest::{header::HeaderMap, multipart, Client, RequestBuilder};
 use serde_json::Value;
 use regex::Regex;
-use sqlx::PgConnection;
-use tokio::task::{self, JoinHandle};
+use tokio::{task::{self, JoinSet}, time::{sleep, timeout}};
 
-use crate::{data::{get_conn, models::{AuthToken, Destination, Source}, types::{Auth, Body}}, utils::Hasher};
+use crate::{data::{models::{Destination, Source}, types::{Auth, Body, Method}}, utils::Hasher};
 pub use self::error::Error;
 use self::response::Response;
+use self::context::RequestContext;
+use self::template::Template;
+use self::circuits::Circuits;
+use self::cache::{CacheLookup, Caches, DestinationCaches};
+use self::filters::Filters;
+use self::routing::RouteSource;
 pub use self::fusion_config::FusionConfig;
+pub use self::routing::RoutingTable;
 
+pub mod admin;
 mod error;
 mod response;
+mod context;
+mod template;
+mod circuits;
+mod cache;
+mod filters;
 mod fusion_config;
+mod routing;
 
-pub async fn entrypoint(request: Request) -> Result<Response, Error> {
-  let (request_parts, _) = request.into_parts();
+pub async fn entrypoint(request: Request, body_limit: usize) -> Result<Response, Error> {
+  let (request_parts, request_body) = request.into_parts();
   let path = format!("/{}", Path::<String>::from_request_parts(&mut request_parts.clone(), &()).await.unwrap().0);
 
-  let mut conn = get_conn().await?;
-
-  let destination = Destination::select_by_path(path, &mut conn).await?;
+  let routing = RoutingTable::current();
+  let (route, path_params) = routing.route(&path).ok_or(Error::NotFound)?;
+  let destination = &route.destination;
 
   if !destination.is_active{
     return Err(Error::NotFound)
   }
 
+  if !Method::from_name(request_parts.method.as_str())
+    .is_some_and(|method| destination.methods.contains(&method)) {
+    return Err(Error::MethodNotAllowed);
+  }
+
   if destination.is_auth {
-    authorize(&request_parts.headers, &destination, &mut conn).await?;
+    authorize(&request_parts.headers, &routing, destination)?;
   }
 
-  let sources = send_source_requests(destination.get_sources(&mut conn).await?).await?;
-  if let Some(filter) = destination.filter {
-    let filtered_result = jq_rs::run(&filter, &sources.to_string())?.trim().to_string();
-    return Ok(Response::JsonString(filtered_result));
+  let body = to_bytes(request_body, body_limit).await?;
+  let passthrough = route.sources.iter().any(|route_source| matches!(route_source.source.body, Body::Passthrough));
+  let body = if body.is_empty() || !passthrough {
+    None
+  } else {
+    Some(serde_json::from_slice::<Value>(&body)
+      .map_err(|error| Error::BadRequest(format!("Request body is not valid JSON: {}", error)))?)
+  };
+
+  let context = Arc::new(RequestContext::new(&request_parts, body, path_params)?);
+  let sources = route.sources.clone();
+
+  let cache_key = destination.cache.as_ref()
+    .map(|_| DestinationCaches::key(&request_parts.method, &path, &context, &route.header_names));
+
+  if let (Some(cache), Some(key)) = (&destination.cache, &cache_key) {
+    if let Some(result) = DestinationCaches::get(&destination.code, cache, key) {
+      println!("Serving cached result for destination: ({})", &destination.code);
+      return Ok(Response::JsonString(result));
+    }
   }
 
-  Ok(Response::JsonString(sources.to_string()))
+  let responses = send_source_requests(sources, context, destination.deadline).await?;
+
+  let codes = |matches: fn(&SourceResponse) -> bool| responses.iter()
+    .filter(|response| matches(response))
+    .map(|response| response.code.as_str())
+    .collect::<Vec<_>>()
+    .join(", ");
+  let stale = codes(|response| response.stale);
+  let failed = codes(|response| response.failed);
+  let sources = Value::Array(responses.into_iter().map(|response| response.value).collect());
+
+  let result = match &destination.filter {
+    Some(filter) => Filters::run(routing.generation, filter, &sources.to_string())?,
+    None => sources.to_string(),
+  };
+
+  let mut headers = HeaderMap::new();
+  for (name, codes) in [("X-Fusion-Stale", stale), ("X-Fusion-Failed", failed)] {
+    if !codes.is_empty() {
+      headers.insert(name, HeaderValue::from_str(&codes)?);
+    }
+  }
+
+  if headers.is_empty() {
+    if let (Some(cache), Some(key)) = (&destination.cache, cache_key) {
+      DestinationCaches::insert(&destination.code, cache, key, result.clone());
+    }
+    Ok(Response::JsonString(result))
+  } else {
+    Ok(Response::JsonStringWithHeaders(result, headers))
+  }
+}
+
+struct SourceResponse {
+  code: String,
+  value: Value,
+  stale: bool,
+  /// Set for an optional source that failed, whose value is `null`.
+  failed: bool,
+}
+
+impl SourceResponse {
+  fn failed(code: &str) -> Self {
+    Self { code: code.to_owned(), value: Value::Null, stale: false, failed: true }
+  }
 }
 
-async fn send_source_requests(sources: Vec<Source>) -> Result<Value, Error> {
-  let mut handles = Vec::<JoinHandle<Result<Value, Error>>>::new();
+/// Runs the sources concurrently, returning their responses in order. An optional source that fails
+/// yields `null`, while a required one failing, or the destination's deadline passing with a required
+/// source still running, aborts the sources still running.
+async fn send_source_requests(sources: Vec<RouteSource>, context: Arc<RequestContext>, deadline: Option<Duration>) -> Result<Vec<SourceResponse>, Error> {
+  let mut tasks = JoinSet::<Result<SourceResponse, Error>>::new();
   let timer = Arc::new(SystemTime::now());
+  let links = sources.iter()
+    .map(|route_source| (route_source.source.code.clone(), route_source.required))
+    .collect::<Vec<_>>();
 
-  for source in sources {
-    let timer = timer.clone();
+  // Task ids map to source indexes, so a task that panics is still matched to its source.
+  let mut indexes = HashMap::new();
+  for (index, route_source) in sources.into_iter().enumerate() {
+    let request = send_source_request(route_source.source, context.clone(), timer.clone());
+    indexes.insert(tasks.spawn(request).id(), index);
+  }
 
-    handles.push(task::spawn(async move {
-      println!("Sending request for url: ({})", &source.url);
+  let mut results = links.iter().map(|_| None).collect::<Vec<_>>();
+  let collect = async {
+    while let Some(result) = tasks.join_next_with_id().await {
+      let (index, response) = match result {
+        Ok((id, response)) => (indexes[&id], response),
+        Err(error) => (indexes[&error.id()], Err(Error::from(error))),
+      };
+      let (code, required) = &links[index];
+      results[index] = Some(match response {
+        Err(error) if !required => {
+          println!("Optional source failed: ({}), {:?}", code, error);
+          SourceResponse::failed(code)
+        },
+        response => response?,
+      });
+    }
+    Ok::<_, Error>(())
+  };
 
-      let client = Client::new();
+  let result = match deadline {
+    Some(deadline) => match timeout(deadline, collect).await {
+      Ok(result) => result,
+      Err(_) if links.iter().zip(&results).all(|((_, required), result)| result.is_some() || !required) => {
+        println!("Deadline of {} ms exceeded, skipping pending optional sources", deadline.as_millis());
+        Ok(())
+      },
+      Err(_) => Err(Error::GatewayTimeout(format!("Deadline of {} ms exceeded", deadline.as_millis()))),
+    },
+    None => collect.await,
+  };
 
-      let request = client
-        .get(&source.url)
-        .query(&source.params)
-        .headers(HeaderMap::<HeaderValue>::try_from(&source.headers)?);
+  if !tasks.is_empty() {
+    println!("Aborting {} pending source requests", tasks.len());
+    tasks.abort_all();
+  }
+  result?;
 
-      let request = match source.timeout.clone() {
-        Some(timeout) => request.timeout(timeout),
-        None => request,
-      };
-  
-      let request = match source.auth {
-        Auth::Basic { username, password } => request.basic_auth(username, Some(password)),
-        Auth::Bearer { token } => request.bearer_auth(token),
-        Auth::Param(key, value) => request.query(&[(key, value)]),
-        Auth::None => request,
-      };
+  Ok(results.into_iter().zip(&links)
+    .map(|(result, (code, _))| result.unwrap_or_else(|| SourceResponse::failed(code)))
+    .collect())
+}
 
-      let request = match source.body {
-        Body::Text(text) => request.body(text),
-        Body::Json(json) => request.json(&json),
-        Body::Form(form) => request.form(&form),
-        Body::Multi(multi) => request.multipart(multi.iter().fold(multipart::Form::new(),
-            |form, (key, val)| form.text(key.to_owned(), val.to_owned()))),
-        Body::None => request,
-      };
+async fn send_source_request(source: Arc<Source>, context: Arc<RequestContext>, timer: Arc<SystemTime>) -> Result<SourceResponse, Error> {
+  let template = Template::new(&context.variables);
+  let url = template.render_url(&source.url)?;
+  let client = Client::new();
+  let request = build_source_request(&client, &source, &url, &template, &context)?;
+
+  let cache_key = source.cache.as_ref()
+    .and_then(|_| request.try_clone()?.build().ok())
+    .and_then(|request| Caches::key(&request));
+
+  let lookup = match (&source.cache, &cache_key) {
+    (Some(cache), Some(key)) => Caches::lookup(&source.code, cache, key),
+    _ => CacheLookup::Miss,
+  };
+
+  let stale = match lookup {
+    CacheLookup::Fresh(value) => {
+      println!("Serving cached response for url: ({})", &url);
+      return Ok(SourceResponse { code: source.code.clone(), value, stale: false, failed: false });
+    },
+    CacheLookup::Revalidate(value, claimed) => {
+      println!("Serving stale response for url: ({})", &url);
+      let code = source.code.clone();
+      if let (true, Some(key)) = (claimed, cache_key) {
+        task::spawn(revalidate_source(source, context.clone(), timer, key));
+      }
+      return Ok(SourceResponse { code, value, stale: true, failed: false });
+    },
+    CacheLookup::Stale(value) => Some(value),
+    CacheLookup::Miss => None,
+  };
+
+  let result = call_source(&source, &url, request,
+    || build_source_request(&client, &source, &url, &template, &context), &timer, cache_key).await;
 
-      let response = match request.send().await {
-        Ok(response) => Ok(response),
-        Err(error) =>
-          if let Some(fallback) = source.fallback {
-            return Ok(fallback)
-          } else {
-            if error.is_timeout() {
-              Err(Error::BadRequest(String::from(format!("Request timeout for source: ({})", &source.url))))
-            } else {
-              Err(Error::InternalServerError(error.to_string()))
-            }
-          }
-      }?;
-
-      println!("Recieved response for url: ({}), took {} ms", &source.url, timer.elapsed()?.as_millis());
-      
-      match response.status() {
-        StatusCode::OK => Ok(response.json().await?),
-        _ =>
-          if let Some(fallback) = source.fallback {
-            Ok(fallback)
-          } else {
-            Err(Error::InternalServerError(String::from(format!("Recieved bad response from source: ({})\nError code {}", &source.url, response.status()))))
-          }
+  match (result, stale, &source.fallback) {
+    (Ok(value), _, _) => Ok(SourceResponse { code: source.code.clone(), value, stale: false, failed: false }),
+    (Err(_), Some(value), _) => {
+      println!("Serving stale response after error for url: ({})", &url);
+      Ok(SourceResponse { code: source.code.clone(), value, stale: true, failed: false })
+    },
+    (Err(_), None, Some(fallback)) => Ok(SourceResponse { code: source.code.clone(), value: fallback.clone(), stale: false, failed: false }),
+    (Err(error), None, None) => Err(error),
+  }
+}
+
+async fn revalidate_source(source: Arc<Source>, context: Arc<RequestContext>, timer: Arc<SystemTime>, key: Vec<u8>) {
+  let template = Template::new(&context.variables);
+  let client = Client::new();
+
+  let result = async {
+    let url = template.render_url(&source.url)?;
+    let request = build_source_request(&client, &source, &url, &template, &context)?;
+    call_source(&source, &url, request,
+      || build_source_request(&client, &source, &url, &template, &context), &timer, Some(key.clone())).await
+  }.await;
+
+  if let Err(error) = result {
+    println!("Revalidation failed for source: ({}), {:?}", &source.code, error);
+  }
+
+  // A response that was not cached, being uncacheable or too large, leaves the entry claimed otherwise.
+  Caches::release(&source.code, &key);
+}
+
+/// Sends the request through the source's circuit breaker and stores a successful response in its cache.
+async fn call_source(
+  source: &Source,
+  url: &str,
+  request: RequestBuilder,
+  rebuild: impl Fn() -> Result<RequestBuilder, Error>,
+  timer: &SystemTime,
+  cache_key: Option<Vec<u8>>,
+) -> Result<Value, Error> {
+  if let Some(breaker) = &source.circuit_breaker {
+    if !Circuits::acquire(&source.code, breaker) {
+      println!("Circuit open, skipping url: ({})", url);
+      return Err(Error::ServiceUnavailable(format!("Circuit open for source: ({})", &source.code)));
+    }
+  }
+
+  let result = fetch_source(source, url, request, rebuild, timer).await;
+
+  if let Some(breaker) = &source.circuit_breaker {
+    Circuits::record(&source.code, breaker, result.is_ok());
+  }
+
+  let (value, headers) = result?;
+
+  if let (Some(cache), Some(key)) = (&source.cache, cache_key) {
+    if let Some(ttl) = cache.expiry(&headers) {
+      Caches::insert(&source.code, cache, key, value.clone(), ttl);
+    }
+  }
+
+  Ok(value)
+}
+
+async fn fetch_source(
+  source: &Source,
+  url: &str,
+  request: RequestBuilder,
+  rebuild: impl Fn() -> Result<RequestBuilder, Error>,
+  timer: &SystemTime,
+) -> Result<(Value, HeaderMap), Error> {
+  let mut request = request;
+  let mut attempt = 1;
+
+  let response = loop {
+    println!("Sending request for url: ({})", url);
+
+    let retry_request = request.try_clone();
+    let result = request.send().await;
+
+    let delay = source.retry.as_ref()
+      .filter(|retry| attempt < retry.max_attempts)
+      .and_then(|retry| match &result {
+        Ok(response) if retry.statuses.contains(&response.status().as_u16()) =>
+          Some(retry.backoff(attempt, Some(response.headers()))),
+        Err(error) if retry.is_retryable(error) => Some(retry.backoff(attempt, None)),
+        _ => None,
+      });
+
+    match delay {
+      Some(delay) => {
+        println!("Retrying url: ({}) in {} ms, attempt {} failed", url, delay.as_millis(), attempt);
+        sleep(delay).await;
+        request = match retry_request {
+          Some(retry_request) => retry_request,
+          None => rebuild()?,
+        };
+        attempt += 1;
+      },
+      None => break result,
+    }
+  };
+
+  let response = response.map_err(|error|
+    if error.is_timeout() {
+      Error::BadRequest(format!("Request timeout for source: ({})", url))
+    } else {
+      Error::InternalServerError(error.to_string())
+    })?;
+
+  println!("Recieved response for url: ({}), took {} ms", url, timer.elapsed()?.as_millis());
+
+  match response.status() {
+    status if status.is_success() => {
+      let headers = response.headers().clone();
+      let content = response.bytes().await?;
+      if source.method == Method::Head || status == StatusCode::NO_CONTENT || content.is_empty() {
+        Ok((Value::Null, headers))
+      } else {
+        Ok((serde_json::from_slice(&content)?, headers))
       }
-    }));
+    },
+    status => Err(Error::InternalServerError(format!("Recieved bad response from source: ({})\nError code {}", url, status))),
   }
+}
 
-  let mut results = Vec::<Value>::new();
-  for handle in handles {
-    results.push(handle.await??);
+fn build_source_request(client: &Client, source: &Source, url: &str, template: &Template, context: &RequestContext) -> Result<RequestBuilder, Error> {
+  let mut headers = HeaderMap::new();
+  for (name, value) in template.render_values(&source.headers)? {
+    let name = HeaderName::try_from(name)?;
+    for value in value.values() {
+      headers.append(&name, HeaderValue::from_str(value)?);
+    }
   }
 
-  Ok(Value::Array(results))
+  let request = client
+    .request(source.method.into(), url)
+    .query(&source.list_format.pairs(&template.render_values(&source.params)?))
+    .query(&context.forwarded_query(&source.forward))
+    .headers(headers)
+    .headers(context.forwarded_headers(&source.forward)?);
+
+  let request = match source.timeout {
+    Some(timeout) => request.timeout(timeout),
+    None => request,
+  };
+
+  let request = match &source.auth {
+    Auth::Basic { username, password } => request.basic_auth(username, Some(password)),
+    Auth::Bearer { token } => request.bearer_auth(token),
+    Auth::Param(key, value) => request.query(&[(key, value)]),
+    Auth::None => request,
+  };
+
+  Ok(match &source.body {
+    Body::Text(text) => request.body(template.render(text)?),
+    Body::Json(json) => request.json(&template.render_json(json)?),
+    Body::Form(form) => request.form(&template.render_map(form)?),
+    Body::Multi(multi) => request.multipart(template.render_map(multi)?.into_iter().fold(multipart::Form::new(),
+        |form, (key, val)| form.text(key, val))),
+    Body::Passthrough => match &context.body {
+      Some(json) => request.json(json),
+      None => request,
+    },
+    Body::None => request,
+  })
 }
 
-async fn authorize(headers: &HeaderMap, destination: &Destination, mut conn: &mut PgConnection) -> Result<(), Error> {
-  let token = AuthToken::select_by_value(
-    Hasher::hash_string(
-      Regex::new(r"^Bearer\s\w{32}$")?
-      .find_iter(headers
-        .get("Authorization")
-        .ok_or(Error::Unauthorized)?.to_str()?).next()
-      .ok_or(Error::Unauthorized)?.as_str().split(' ').last()
-      .ok_or(Error::Unauthorized)?.to_owned()
-    ), &mut conn).await?
-    .ok_or(Error::Unauthorized)?;
+fn authorize(headers: &HeaderMap, routing: &RoutingTable, destination: &Destination) -> Result<(), Error> {
+  let token_hash = Hasher::hash_string(
+    Regex::new(r"^Bearer\s\w{32}$")?
+    .find_iter(headers
+      .get("Authorization")
+      .ok_or(Error::Unauthorized)?.to_str()?).next()
+    .ok_or(Error::Unauthorized)?.as_str().split(' ').next_back()
+    .ok_or(Error::Unauthorized)?.to_owned()
+  );
   
-  if !destination.is_token_for(&token, &mut conn).await? {
+  if !routing.is_granted(&token_hash, destination) {
     return Err(Error::Unauthorized);
   }
 
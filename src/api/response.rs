@@ -1,8 +1,10 @@
 use axum::{body::Body, response::{IntoResponse, Response as AxumResponse}};
+use http::HeaderMap;
 use reqwest::StatusCode;
 
 pub enum Response {
-  JsonString(String)
+  JsonString(String),
+  JsonStringWithHeaders(String, HeaderMap),
 }
 
 impl IntoResponse for Response {
@@ -13,6 +15,11 @@ impl IntoResponse for Response {
         .header("Content-Type", "application/json")
         .body(Body::new(json))
         .unwrap(),
+      Self::JsonStringWithHeaders(json, headers) => {
+        let mut response = Self::JsonString(json).into_response();
+        response.headers_mut().extend(headers);
+        response
+      },
     }
   }
 }
\ No newline at end of file
@@ -0,0 +1,120 @@
+use std::{
+  collections::{BTreeSet, HashMap, HashSet},
+  sync::{atomic::{AtomicU64, Ordering}, Arc, OnceLock, PoisonError, RwLock},
+};
+
+use sqlx::Executor;
+
+use crate::{data::{get_tran, models::{AuthToken, Destination, Source}, Error}, utils::PathPattern};
+use super::cache::DestinationCaches;
+
+static ROUTING: OnceLock<RwLock<Arc<RoutingTable>>> = OnceLock::new();
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub struct Route {
+  pub destination: Destination,
+  pub sources: Vec<RouteSource>,
+  /// Inbound headers that go into destination cache keys, gathered once from the sources.
+  pub header_names: BTreeSet<String>,
+  pattern: PathPattern,
+}
+
+#[derive(Clone)]
+pub struct RouteSource {
+  pub source: Arc<Source>,
+  /// Whether the destination fails when this source does, rather than returning a partial result.
+  pub required: bool,
+}
+
+struct Grant {
+  token: AuthToken,
+  destinations: HashSet<i32>,
+}
+
+/// Immutable snapshot of destinations, their sources and token grants, swapped whole after each config update.
+#[derive(Default)]
+pub struct RoutingTable {
+  /// Increases with every reload, so per-thread caches derived from the table know when to reset.
+  pub generation: u64,
+  routes: Vec<Route>,
+  grants: HashMap<Vec<u8>, Grant>,
+}
+
+impl RoutingTable {
+  pub fn current() -> Arc<Self> {
+    ROUTING.get_or_init(Default::default)
+      .read().unwrap_or_else(PoisonError::into_inner)
+      .clone()
+  }
+
+  pub async fn reload() -> Result<(), Error> {
+    let table = Arc::new(Self::load().await?);
+    println!("Routing table loaded with {} destinations.", table.routes.len());
+
+    *ROUTING.get_or_init(Default::default)
+      .write().unwrap_or_else(PoisonError::into_inner) = table;
+    DestinationCaches::clear();
+
+    Ok(())
+  }
+
+  async fn load() -> Result<Self, Error> {
+    let mut tran = get_tran().await?;
+    (&mut *tran).execute("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY;").await?;
+
+    let destinations = Destination::select_all(&mut tran).await?;
+    let sources = Source::select_all(&mut tran).await?.into_iter()
+      .filter_map(|source| source.id.map(|id| (id, Arc::new(source))))
+      .collect::<HashMap<_, _>>();
+    let source_links = Destination::select_source_links(&mut tran).await?;
+    let tokens = AuthToken::select_all(&mut tran).await?;
+    let token_links = AuthToken::select_destination_links(&mut tran).await?;
+
+    tran.commit().await?;
+
+    let routes = destinations.into_iter()
+      .map(|destination| {
+        let route_sources = source_links.iter()
+          .filter(|(destination_id, _, _)| Some(*destination_id) == destination.id)
+          .filter_map(|(_, source_id, required)| Some(RouteSource {
+            source: sources.get(source_id)?.clone(),
+            required: *required,
+          }))
+          .collect::<Vec<_>>();
+        Route {
+          header_names: DestinationCaches::header_names(&route_sources),
+          pattern: PathPattern::new(&destination.path),
+          sources: route_sources,
+          destination,
+        }
+      })
+      .collect();
+
+    let grants = tokens.into_iter()
+      .map(|token| (token.value.clone(), Grant {
+        destinations: token_links.iter()
+          .filter(|(token_id, _)| Some(*token_id) == token.id)
+          .map(|(_, destination_id)| *destination_id)
+          .collect(),
+        token,
+      }))
+      .collect();
+
+    Ok(Self { generation: GENERATION.fetch_add(1, Ordering::Relaxed) + 1, routes, grants })
+  }
+
+  /// Finds the most specific route matching `path`, along with its captured path params.
+  pub fn route(&self, path: &str) -> Option<(&Route, HashMap<String, String>)> {
+    self.routes.iter()
+      .filter_map(|route| route.pattern.captures(path).map(|captures| (route.pattern.specificity(), route, captures)))
+      .reduce(|best, next| if next.0 > best.0 { next } else { best })
+      .map(|(_, route, captures)| (route, captures))
+  }
+
+  pub fn is_granted(&self, token_hash: &[u8], destination: &Destination) -> bool {
+    self.grants.get(token_hash)
+      .filter(|grant| grant.token.is_valid())
+      .zip(destination.id)
+      .is_some_and(|(grant, id)| grant.destinations.contains(&id))
+  }
+}
\ No newline at end of file
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::data::{models::Source, types::{Body, MultiValue}};
+use super::Error;
+
+const URL_VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+static VARIABLE: OnceLock<Regex> = OnceLock::new();
+
+fn variable() -> &'static Regex {
+  VARIABLE.get_or_init(|| Regex::new(r"\{(path|query|header)\.([^{}\s]+)\}").unwrap())
+}
+
+pub struct Template<'a> {
+  variables: &'a HashMap<String, String>,
+}
+
+impl<'a> Template<'a> {
+  pub fn new(variables: &'a HashMap<String, String>) -> Self {
+    Self { variables }
+  }
+
+  /// Lowercased names of the inbound headers that `{header.*}` variables refer to in the parts of
+  /// `source` rendered per request: its url, param and header values and body.
+  pub fn header_names(source: &Source) -> impl Iterator<Item = String> + '_ {
+    let mut inputs = vec![source.url.as_str()];
+    inputs.extend(source.params.values().chain(source.headers.values())
+      .flat_map(MultiValue::values)
+      .map(String::as_str));
+    match &source.body {
+      Body::Text(text) => inputs.push(text),
+      Body::Json(json) => Self::json_strings(json, &mut inputs),
+      Body::Form(values) | Body::Multi(values) => inputs.extend(values.values().map(String::as_str)),
+      Body::None | Body::Passthrough => {},
+    }
+
+    inputs.into_iter()
+      .flat_map(|input| variable().captures_iter(input))
+      .filter(|captures| &captures[1] == "header")
+      .map(|captures| captures[2].to_lowercase())
+  }
+
+  pub fn render(&self, input: &str) -> Result<String, Error> {
+    self.render_with(input, |value| value.to_owned())
+  }
+
+  pub fn render_url(&self, input: &str) -> Result<String, Error> {
+    self.render_with(input, |value| utf8_percent_encode(value, URL_VALUE).to_string())
+  }
+
+  pub fn render_map(&self, map: &HashMap<String, String>) -> Result<HashMap<String, String>, Error> {
+    map.iter()
+      .map(|(key, value)| Ok((key.to_owned(), self.render(value)?)))
+      .collect()
+  }
+
+  pub fn render_values(&self, map: &HashMap<String, MultiValue>) -> Result<HashMap<String, MultiValue>, Error> {
+    map.iter()
+      .map(|(key, value)| Ok((key.to_owned(), value.try_map(|value| self.render(value))?)))
+      .collect()
+  }
+
+  pub fn render_json(&self, value: &Value) -> Result<Value, Error> {
+    Ok(match value {
+      Value::String(string) => Value::String(self.render(string)?),
+      Value::Array(array) => Value::Array(array.iter()
+        .map(|val| self.render_json(val))
+        .collect::<Result<_, _>>()?),
+      Value::Object(object) => Value::Object(object.iter()
+        .map(|(key, val)| Ok((key.to_owned(), self.render_json(val)?)))
+        .collect::<Result<_, Error>>()?),
+      _ => value.clone(),
+    })
+  }
+
+  /// Collects the strings in `value` that [`Self::render_json`] renders.
+  fn json_strings<'v>(value: &'v Value, strings: &mut Vec<&'v str>) {
+    match value {
+      Value::String(string) => strings.push(string),
+      Value::Array(array) => array.iter().for_each(|value| Self::json_strings(value, strings)),
+      Value::Object(object) => object.values().for_each(|value| Self::json_strings(value, strings)),
+      _ => {},
+    }
+  }
+
+  fn render_with(&self, input: &str, encode: impl Fn(&str) -> String) -> Result<String, Error> {
+    let mut missing = None;
+
+    let rendered = variable()
+      .replace_all(input, |captures: &Captures| {
+        let name = match &captures[1] {
+          "header" => format!("header.{}", captures[2].to_lowercase()),
+          kind => format!("{}.{}", kind, &captures[2]),
+        };
+        match self.variables.get(&name) {
+          Some(value) => encode(value),
+          None => {
+            missing.get_or_insert(name);
+            String::new()
+          },
+        }
+      })
+      .into_owned();
+
+    match missing {
+      Some(name) if name.starts_with("path.") =>
+        Err(Error::InternalServerError(format!("Template variable `{}` is not defined.", name))),
+      Some(name) => Err(Error::BadRequest(format!("Request is missing `{}`.", name))),
+      None => Ok(rendered),
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,70 @@
+use crate::data::get_conn;
+
+use super::{plan_config, ConfigVersion, Error};
+
+/// Runs a config command given on the command line, returning whether one was run.
+///
+/// - `plan` / `--dry-run`: diff the config file against the database.
+/// - `versions`: list applied config versions.
+/// - `diff <from> <to>`: diff two versions.
+/// - `rollback <version>`: reapply a version's snapshot.
+///
+/// Versions may be given as a unique id prefix; `--json` prints JSON instead of text.
+pub async fn run_command(args: &[String]) -> Result<bool, Error> {
+  let json = args.iter().any(|arg| arg == "--json");
+  let dry_run = args.iter().any(|arg| arg == "--dry-run");
+  let args = args.iter()
+    .filter(|arg| !arg.starts_with("--"))
+    .map(String::as_str)
+    .collect::<Vec<_>>();
+
+  match (dry_run, args.as_slice()) {
+    (true, []) | (_, ["plan"]) => {
+      let plan = plan_config().await?;
+      if json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+      } else {
+        print!("{}", plan);
+      }
+    },
+    (false, ["versions"]) => {
+      let versions = ConfigVersion::select_all(&mut get_conn().await?).await?;
+      if json {
+        println!("{}", serde_json::to_string_pretty(&versions)?);
+      } else {
+        for version in versions {
+          print!("{}  {}  {}", version.id, version.updated_on, &version.hash[..12]);
+          if let Some(profile) = &version.profile {
+            print!("  [{}]", profile);
+          }
+          if let Some(rollback_of) = &version.rollback_of {
+            print!("  (rollback of {})", rollback_of);
+          }
+          if !version.has_snapshot {
+            print!("  (no snapshot)");
+          }
+          println!();
+        }
+      }
+    },
+    (false, ["diff", from, to]) => {
+      let mut conn = get_conn().await?;
+      let plan = ConfigVersion::select_by_prefix(from, &mut conn).await?
+        .diff(&ConfigVersion::select_by_prefix(to, &mut conn).await?)?;
+      if json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+      } else {
+        print!("{}", plan);
+      }
+    },
+    (false, ["rollback", version]) => {
+      ConfigVersion::select_by_prefix(version, &mut get_conn().await?).await?
+        .rollback().await?;
+      println!("Rolled back, send SIGHUP to running instances to reload routing.");
+    },
+    (false, []) => return Ok(false),
+    _ => Err(Error::Str("Usage: fusion [plan | --dry-run | versions | diff <from> <to> | rollback <version>] [--json]"))?,
+  }
+
+  Ok(true)
+}
\ No newline at end of file
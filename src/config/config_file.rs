@@ -1,4 +1,4 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{fmt::{self, Display, Formatter}, fs::File, io::Read, path::Path};
 
 use super::Error;
 
@@ -8,9 +8,9 @@ pub enum FileType {
   Fallback,
 }
 
-impl ToString for FileType {
-  fn to_string(&self) -> String {
-    String::from(match self {
+impl Display for FileType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
       Self::Config => "Config",
       Self::Filter => "Filter",
       Self::Fallback => "Fallback",
@@ -51,7 +51,7 @@ impl<'a> ConfigFile<'a> {
       },
       (file_type, _) => Err(Error::String(
         format!("{} file `{}` extension invalid.",
-          file_type.to_string(), self.path.to_str().unwrap())))?,
+          file_type, self.path.to_str().unwrap())))?,
     })
   }
 }
\ No newline at end of file
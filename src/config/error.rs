@@ -5,6 +5,7 @@ use serde_yaml::Error as YamlError;
 use crate::data::Error as DataError;
 use sqlx::Error as SqlxError;
 use serde_json::Error as JsonError;
+use regex::Error as RegexError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -18,12 +19,18 @@ pub enum Error {
   Json(JsonError),
   #[error("DATABASE ERROR: `{0}`")]
   Database(DataError),
+  #[error("REGEX ERROR: `{0}`")]
+  Regex(RegexError),
   #[error("DATETIME PARSE ERROR: `{0}`")]
   Chrono(ChronoParseError),
   #[error("ERROR: `{0}`")]
   Str(&'static str),
   #[error("ERROR: `{0}`")]
   String(String),
+  #[error("SCHEMA ERROR: {0}")]
+  Schema(String),
+  #[error("VALIDATION ERROR:\n{}", .0.join("\n"))]
+  Validation(Vec<String>),
 }
 
 impl From<EnvError> for Error {
@@ -66,4 +73,10 @@ impl From<JsonError> for Error {
   fn from(value: JsonError) -> Self {
     Self::Json(value)
   }
+}
+
+impl From<RegexError> for Error {
+  fn from(value: RegexError) -> Self {
+    Self::Regex(value)
+  }
 }
\ No newline at end of file
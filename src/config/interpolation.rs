@@ -0,0 +1,190 @@
+use std::{fs, sync::OnceLock};
+
+use regex::{Captures, Regex};
+use serde_yaml::Value as YamlValue;
+
+use super::{Error, YamlParser};
+
+static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+
+/// Resolves `${NAME}`, `${NAME:-default}` and `${file:/path}` placeholders in config string values.
+///
+/// `$${...}` is left in place as a literal `${...}`.
+pub struct Interpolation;
+
+impl Interpolation {
+  /// Resolves every string value, reporting each placeholder that cannot be resolved with its YAML path.
+  pub fn resolve(mut config: YamlValue) -> Result<YamlValue, Error> {
+    let mut errors = Vec::new();
+
+    Self::walk(Self::regex(), &mut config, "", &mut errors);
+
+    if errors.is_empty() {
+      Ok(config)
+    } else {
+      Err(Error::Validation(errors))
+    }
+  }
+
+  /// Paths of the secret files referenced by `${file:...}` placeholders.
+  pub fn files(config: &YamlValue) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    Self::collect_files(Self::regex(), config, &mut files);
+
+    Ok(files)
+  }
+
+  /// Resolves the placeholders in a single string.
+  pub fn resolve_str(value: &str) -> Result<String, Error> {
+    YamlParser::to_string(&Self::resolve(YamlValue::from(value))?)
+  }
+
+  /// Whether `value` holds a placeholder still to be resolved.
+  pub fn is_unresolved(value: &str) -> bool {
+    Self::regex().captures_iter(value).any(|captures| captures[1].is_empty())
+  }
+
+  /// Escapes `value` so it resolves back to itself, for file contents inlined into a config.
+  pub fn escape(value: &str) -> String {
+    value.replace("${", "$${")
+  }
+
+  fn regex() -> &'static Regex {
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\$(\$?)\{([^{}]*)\}").unwrap())
+  }
+
+  fn walk(regex: &Regex, value: &mut YamlValue, path: &str, errors: &mut Vec<String>) {
+    match value {
+      YamlValue::String(string) => {
+        let mut failures = Vec::new();
+        let resolved = regex.replace_all(string, |captures: &Captures| {
+          match Self::placeholder(&captures[1], &captures[2]) {
+            Ok(value) => value,
+            Err(error) => {
+              failures.push(format!("`{}`: {}", path, error));
+              String::new()
+            },
+          }
+        }).into_owned();
+
+        *string = resolved;
+        errors.append(&mut failures);
+      },
+      YamlValue::Mapping(mapping) => {
+        for (key, value) in mapping.iter_mut() {
+          let key = key.as_str().map(str::to_owned)
+            .unwrap_or_else(|| serde_yaml::to_string(key).unwrap_or_default().trim().to_owned());
+          let path = if path.is_empty() { key } else { format!("{}.{}", path, key) };
+          Self::walk(regex, value, &path, errors);
+        }
+      },
+      YamlValue::Sequence(sequence) => {
+        for (index, value) in sequence.iter_mut().enumerate() {
+          Self::walk(regex, value, &format!("{}[{}]", path, index), errors);
+        }
+      },
+      YamlValue::Tagged(tagged) => Self::walk(regex, &mut tagged.value, path, errors),
+      _ => {},
+    }
+  }
+
+  fn placeholder(escape: &str, expression: &str) -> Result<String, String> {
+    if !escape.is_empty() {
+      return Ok(format!("${{{}}}", expression));
+    }
+
+    if let Some(path) = expression.strip_prefix("file:") {
+      let mut content = fs::read_to_string(path)
+        .map_err(|error| format!("secret file `{}` could not be read: {}.", path, error))?;
+      if content.ends_with('\n') {
+        content.pop();
+        if content.ends_with('\r') {
+          content.pop();
+        }
+      }
+      return Ok(content);
+    }
+
+    let (name, default) = match expression.split_once(":-") {
+      Some((name, default)) => (name, Some(default)),
+      None => (expression, None),
+    };
+
+    if name.is_empty() || !name.chars().all(|char| char.is_ascii_alphanumeric() || char == '_') {
+      return Err(format!("placeholder `${{{}}}` invalid.", expression));
+    }
+
+    match (dotenv::var(name).ok(), default) {
+      (Some(value), Some(default)) if value.is_empty() => Ok(default.to_owned()),
+      (Some(value), _) => Ok(value),
+      (None, Some(default)) => Ok(default.to_owned()),
+      (None, None) => Err(format!("environment variable `{}` is not set.", name)),
+    }
+  }
+
+  fn collect_files(regex: &Regex, value: &YamlValue, files: &mut Vec<String>) {
+    match value {
+      YamlValue::String(string) => files.extend(regex.captures_iter(string)
+        .filter(|captures| captures[1].is_empty())
+        .filter_map(|captures| captures[2].strip_prefix("file:").map(str::to_owned))),
+      YamlValue::Mapping(mapping) => mapping.values()
+        .for_each(|value| Self::collect_files(regex, value, files)),
+      YamlValue::Sequence(sequence) => sequence.iter()
+        .for_each(|value| Self::collect_files(regex, value, files)),
+      YamlValue::Tagged(tagged) => Self::collect_files(regex, &tagged.value, files),
+      _ => {},
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env;
+
+  use super::*;
+
+  #[test]
+  fn keeps_escaped_placeholders_literal() {
+    assert_eq!(Interpolation::placeholder("$", "INTERPOLATION_UNSET").unwrap(), "${INTERPOLATION_UNSET}");
+    assert_eq!(Interpolation::placeholder("$", "file:/missing").unwrap(), "${file:/missing}");
+  }
+
+  #[test]
+  fn reads_environment_variables() {
+    env::set_var("INTERPOLATION_SET", "value");
+    env::set_var("INTERPOLATION_EMPTY", "");
+
+    assert_eq!(Interpolation::placeholder("", "INTERPOLATION_SET").unwrap(), "value");
+    assert_eq!(Interpolation::placeholder("", "INTERPOLATION_SET:-default").unwrap(), "value");
+    assert_eq!(Interpolation::placeholder("", "INTERPOLATION_EMPTY").unwrap(), "");
+  }
+
+  #[test]
+  fn falls_back_to_defaults() {
+    env::set_var("INTERPOLATION_BLANK", "");
+
+    assert_eq!(Interpolation::placeholder("", "INTERPOLATION_UNSET:-default").unwrap(), "default");
+    assert_eq!(Interpolation::placeholder("", "INTERPOLATION_BLANK:-default").unwrap(), "default");
+    assert_eq!(Interpolation::placeholder("", "INTERPOLATION_UNSET:-").unwrap(), "");
+  }
+
+  #[test]
+  fn rejects_unset_and_invalid_names() {
+    assert!(Interpolation::placeholder("", "INTERPOLATION_UNSET").is_err());
+    assert!(Interpolation::placeholder("", "").is_err());
+    assert!(Interpolation::placeholder("", "NOT-VALID").is_err());
+    assert!(Interpolation::placeholder("", ":-default").is_err());
+  }
+
+  #[test]
+  fn reads_secret_files_without_the_trailing_newline() {
+    let path = env::temp_dir().join(format!("interpolation-{}", std::process::id()));
+    fs::write(&path, "secret\r\n").unwrap();
+
+    let secret = Interpolation::placeholder("", &format!("file:{}", path.display()));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(secret.unwrap(), "secret");
+    assert!(Interpolation::placeholder("", "file:/nonexistent/secret").is_err());
+  }
+}
\ No newline at end of file
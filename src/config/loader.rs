@@ -0,0 +1,444 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fmt,
+  fs,
+  path::{Path, PathBuf},
+};
+
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_yaml::{Location, Mapping, Value as YamlValue};
+
+use super::{config_file::{ConfigFile, FileType}, schema::ConfigSchema, Error, YamlParser};
+
+/// Reads a config file along with everything it pulls in through `include:`, merged into one config.
+///
+/// Include entries are resolved against the including file's directory and may name a file, a
+/// directory (every `.yaml`/`.yml` file in it, by name) or a file pattern using `*`.
+/// Mapping sections are merged key by key, and a key declared in two files is an error; sequence
+/// sections are concatenated.
+pub struct ConfigLoader {
+  merged: Mapping,
+  origins: Origins,
+  loaded: HashSet<PathBuf>,
+  watched: Vec<String>,
+  errors: Vec<String>,
+}
+
+impl ConfigLoader {
+  /// Returns the merged config, every file and directory read to build it and where each entry
+  /// was declared.
+  pub fn load(path: &str) -> Result<(YamlValue, Vec<String>, Origins), Error> {
+    let mut loader = Self {
+      merged: Mapping::new(),
+      origins: Origins::default(),
+      loaded: HashSet::new(),
+      watched: Vec::new(),
+      errors: Vec::new(),
+    };
+
+    loader.read(Path::new(path))?;
+
+    if loader.errors.is_empty() {
+      Ok((YamlValue::Mapping(loader.merged), loader.watched, loader.origins))
+    } else {
+      Err(Error::Validation(loader.errors))
+    }
+  }
+
+  fn read(&mut self, path: &Path) -> Result<(), Error> {
+    if !path.is_file() {
+      return Err(Error::String(format!("Config file `{}` not found.", path.display())));
+    }
+
+    if !self.loaded.insert(path.canonicalize()?) {
+      return Ok(());
+    }
+
+    let file = path.to_string_lossy().into_owned();
+    let content = ConfigFile::new(&file, FileType::Config)?.read()?;
+    ConfigSchema::validate(&file, &content)?;
+    self.watched.push(file.clone());
+
+    let YamlValue::Mapping(config) = serde_yaml::from_str::<YamlValue>(&content)? else {
+      return Ok(());
+    };
+
+    for (section, value) in config {
+      let name = YamlParser::to_string(&section)?;
+
+      if name == "include" {
+        let base = path.parent().unwrap_or(Path::new("."));
+        for include in YamlParser::vec_to_string(YamlParser::to_sequence_option(Some(&value))?.unwrap_or(&Vec::new()))? {
+          for include in self.resolve(&base.join(include))? {
+            self.read(&include)?;
+          }
+        }
+        continue;
+      }
+
+      // A section left empty, such as `sources:` alone, declares nothing.
+      if value.is_null() {
+        continue;
+      }
+
+      match (self.merged.get_mut(&section), value) {
+        (None, value) => {
+          match &value {
+            YamlValue::Mapping(entries) => for key in entries.keys() {
+              let entry = Self::entry(&name, key);
+              self.origins.insert(entry.clone(), &file, entry);
+            },
+            YamlValue::Sequence(entries) => for index in 0..entries.len() {
+              let entry = format!("{}[{}]", name, index);
+              self.origins.insert(entry.clone(), &file, entry);
+            },
+            _ => {},
+          }
+          self.origins.insert(name.clone(), &file, name);
+          self.merged.insert(section, value);
+        },
+        (Some(YamlValue::Mapping(merged)), YamlValue::Mapping(entries)) => {
+          for (key, value) in entries {
+            let entry = Self::entry(&name, &key);
+            match self.origins.file(&entry) {
+              Some(origin) => self.errors.push(format!("`{}`: declared in both `{}` and `{}`.", entry, origin, file)),
+              None => {
+                self.origins.insert(entry.clone(), &file, entry);
+                merged.insert(key, value);
+              },
+            }
+          }
+        },
+        (Some(YamlValue::Sequence(merged)), YamlValue::Sequence(entries)) => {
+          for (index, value) in entries.into_iter().enumerate() {
+            self.origins.insert(format!("{}[{}]", name, merged.len()), &file, format!("{}[{}]", name, index));
+            merged.push(value);
+          }
+        },
+        (Some(_), _) => self.errors.push(format!("`{}`: declared in both `{}` and `{}`.",
+          name, self.origins.file(&name).unwrap_or_default(), file)),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Expands an include entry into the config files it names, in name order.
+  fn resolve(&mut self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let (directory, pattern) = if path.is_dir() {
+      (path.to_path_buf(), None)
+    } else {
+      match path.file_name().map(|name| name.to_string_lossy()).filter(|name| name.contains('*')) {
+        Some(pattern) => (path.parent().unwrap_or(Path::new(".")).to_path_buf(), Some(pattern.into_owned())),
+        None => return Ok(vec![path.to_path_buf()]),
+      }
+    };
+
+    self.watched.push(directory.to_string_lossy().into_owned());
+
+    let mut files = fs::read_dir(&directory)?
+      .map(|entry| Ok(entry?.path()))
+      .collect::<Result<Vec<_>, Error>>()?
+      .into_iter()
+      .filter(|file| file.is_file())
+      .filter(|file| {
+        let name = file.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        match &pattern {
+          Some(pattern) => Self::matches(pattern, &name),
+          None => name.ends_with(".yaml") || name.ends_with(".yml"),
+        }
+      })
+      .collect::<Vec<_>>();
+    files.sort();
+
+    Ok(files)
+  }
+
+  /// Matches `name` against `pattern`, where `*` stands for any run of characters.
+  fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+      return false;
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+      return rest.is_empty();
+    };
+
+    for part in middle {
+      match rest.find(part) {
+        Some(index) => rest = &rest[index + part.len()..],
+        None => return false,
+      }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+  }
+
+  fn entry(section: &str, key: &YamlValue) -> String {
+    format!("{}.{}", section, key.as_str().unwrap_or_default())
+  }
+}
+
+/// Where the entries of a merged config were declared, to point errors found after merging at the
+/// file and line that caused them.
+///
+/// Each entry (`sources.<code>`, `auth_tokens[<index>]`, ...) keeps every file declaring it, in
+/// merge order, with its path in that file: concatenated sequence items move to a new index.
+#[derive(Default)]
+pub struct Origins(HashMap<String, Vec<(String, String)>>);
+
+impl Origins {
+  /// Adds the entries of `overlay`, merged onto this config, so that its declarations are looked up first.
+  pub fn extend(&mut self, overlay: Origins) {
+    for (entry, origins) in overlay.0 {
+      self.0.entry(entry).or_default().extend(origins);
+    }
+  }
+
+  /// Formats `message` about `path` in the merged config like schema errors,
+  /// `file:line:column: \`path\`: message`, pointing at the closest value a declaring file holds.
+  pub fn error(&self, path: &str, message: &str) -> String {
+    match self.locate(path) {
+      Some(location) => format!("{}: `{}`: {}", location, path, message),
+      None => format!("`{}`: {}", path, message),
+    }
+  }
+
+  fn insert(&mut self, entry: String, file: &str, path: String) {
+    self.0.entry(entry).or_default().push((file.to_owned(), path));
+  }
+
+  fn file(&self, entry: &str) -> Option<&str> {
+    self.0.get(entry)?.first().map(|(file, _)| file.as_str())
+  }
+
+  fn locate(&self, path: &str) -> Option<String> {
+    let segments = Segment::parse(path);
+    let split = segments.len().min(2);
+    let origins = self.0.get(&Segment::join(&segments[..split]))?;
+
+    for depth in (split..=segments.len()).rev() {
+      for (file, entry) in origins.iter().rev() {
+        let mut local = Segment::parse(entry);
+        local.extend_from_slice(&segments[split..depth]);
+
+        if let Some(location) = Self::find(file, &local) {
+          return Some(format!("{}:{}:{}", file, location.line(), location.column()));
+        }
+      }
+    }
+
+    origins.last().map(|(file, _)| file.clone())
+  }
+
+  /// Location of the value at `path` in `file`, found by reading the file until that value and
+  /// failing there, which gives the failure its location.
+  fn find(file: &str, path: &[Segment]) -> Option<Location> {
+    let content = fs::read_to_string(file).ok()?;
+    let error = Locate(path).deserialize(serde_yaml::Deserializer::from_str(&content)).err()?;
+
+    if error.to_string().contains(Locate::FOUND) {
+      error.location()
+    } else {
+      None
+    }
+  }
+}
+
+#[derive(Clone)]
+enum Segment {
+  Key(String),
+  Index(usize),
+}
+
+impl Segment {
+  /// Splits a path such as `destinations.d.sources[1]` into its keys and indexes.
+  fn parse(path: &str) -> Vec<Self> {
+    path.split('.')
+      .flat_map(|part| {
+        let mut parts = part.split('[');
+        let key = parts.next().unwrap_or_default();
+        Some(Self::Key(key.to_owned())).filter(|_| !key.is_empty()).into_iter()
+          .chain(parts.filter_map(|index| index.strip_suffix(']')?.parse().ok()).map(Self::Index))
+          .collect::<Vec<_>>()
+      })
+      .collect()
+  }
+
+  fn join(segments: &[Self]) -> String {
+    segments.iter().enumerate()
+      .map(|(index, segment)| match segment {
+        Self::Key(key) if index == 0 => key.clone(),
+        Self::Key(key) => format!(".{}", key),
+        Self::Index(index) => format!("[{}]", index),
+      })
+      .collect()
+  }
+}
+
+/// Walks a document down to the value at a path and fails with [`Locate::FOUND`] there.
+struct Locate<'a>(&'a [Segment]);
+
+impl Locate<'_> {
+  const FOUND: &'static str = "value located";
+
+  fn scalar<E: de::Error>(self) -> Result<(), E> {
+    match self.0.is_empty() {
+      true => Err(E::custom(Self::FOUND)),
+      false => Ok(()),
+    }
+  }
+}
+
+impl<'de> DeserializeSeed<'de> for Locate<'_> {
+  type Value = ();
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+    deserializer.deserialize_any(self)
+  }
+}
+
+impl<'de> Visitor<'de> for Locate<'_> {
+  type Value = ();
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("any value")
+  }
+
+  fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+    self.scalar()
+  }
+
+  fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+    self.scalar()
+  }
+
+  fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+    self.scalar()
+  }
+
+  fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+    self.scalar()
+  }
+
+  fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+    self.scalar()
+  }
+
+  fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+    self.scalar()
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+    let Some((Segment::Key(name), rest)) = self.0.split_first() else {
+      return self.scalar();
+    };
+
+    while let Some(key) = map.next_key::<YamlValue>()? {
+      match key.as_str() == Some(name) {
+        true => map.next_value_seed(Locate(rest))?,
+        false => map.next_value::<IgnoredAny>().map(|_| ())?,
+      }
+    }
+
+    Ok(())
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+    let Some((Segment::Index(position), rest)) = self.0.split_first() else {
+      return self.scalar();
+    };
+
+    let mut index = 0;
+    loop {
+      let found = match index == *position {
+        true => seq.next_element_seed(Locate(rest))?.is_some(),
+        false => seq.next_element::<IgnoredAny>()?.is_some(),
+      };
+      if !found {
+        return Ok(());
+      }
+      index += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn matches_names_without_wildcards_exactly() {
+    assert!(ConfigLoader::matches("sources.yaml", "sources.yaml"));
+    assert!(!ConfigLoader::matches("sources.yaml", "sources.yml"));
+    assert!(!ConfigLoader::matches("sources.yaml", "sources.yaml.bak"));
+  }
+
+  #[test]
+  fn matches_wildcards_at_either_end() {
+    assert!(ConfigLoader::matches("*.yaml", "sources.yaml"));
+    assert!(ConfigLoader::matches("*.yaml", ".yaml"));
+    assert!(ConfigLoader::matches("sources*", "sources.prod.yaml"));
+    assert!(ConfigLoader::matches("*", "anything"));
+    assert!(!ConfigLoader::matches("*.yaml", "sources.yml"));
+  }
+
+  #[test]
+  fn matches_wildcards_in_the_middle() {
+    assert!(ConfigLoader::matches("sources.*.yaml", "sources.prod.yaml"));
+    assert!(ConfigLoader::matches("a*b*c", "abc"));
+    assert!(ConfigLoader::matches("a*b*c", "a-b-b-c"));
+    assert!(!ConfigLoader::matches("a*b*c", "a-c-b"));
+  }
+
+  #[test]
+  fn does_not_reuse_characters_across_parts() {
+    assert!(!ConfigLoader::matches("a*a", "a"));
+    assert!(!ConfigLoader::matches("ab*bc", "abc"));
+    assert!(ConfigLoader::matches("ab*bc", "abbc"));
+  }
+
+  /// Writes `files` into a fresh directory named after the test, returning the first one's path.
+  fn write(test: &str, files: &[(&str, &str)]) -> String {
+    let directory = std::env::temp_dir().join(format!("fusion-loader-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for (name, content) in files {
+      fs::write(directory.join(name), content).unwrap();
+    }
+
+    directory.join(files[0].0).to_string_lossy().into_owned()
+  }
+
+  #[test]
+  fn locates_entries_in_the_file_declaring_them() {
+    let path = write("locate", &[
+      ("main.yaml", "include: [extra.yaml]\nauth_tokens:\n  - value: a\n    destinations: [d, e]\n"),
+      ("extra.yaml", "destinations:\n  d:\n    path: /d\n    sources:\n      - s\nauth_tokens:\n  - value: b\n"),
+    ]);
+    let extra = path.replace("main.yaml", "extra.yaml");
+    let origins = ConfigLoader::load(&path).unwrap().2;
+
+    assert_eq!(origins.error("destinations.d.sources[0]", "unknown source `s`."),
+      format!("{}:5:9: `destinations.d.sources[0]`: unknown source `s`.", extra));
+    assert_eq!(origins.error("auth_tokens[1].destinations[1]", "unknown destination `e`."),
+      format!("{}:4:23: `auth_tokens[1].destinations[1]`: unknown destination `e`.", path));
+    assert_eq!(origins.error("destinations.d.filter", "jq filter does not compile."),
+      format!("{}:3:5: `destinations.d.filter`: jq filter does not compile.", extra));
+    assert_eq!(origins.error("sources.s", "missing field `url`."), "`sources.s`: missing field `url`.");
+  }
+
+  #[test]
+  fn merges_sections_left_empty_in_another_file() {
+    let path = write("empty", &[
+      ("main.yaml", "include: [extra.yaml]\nsources:\ndestinations:\n  d:\n    path: /d\n"),
+      ("extra.yaml", "sources:\n  s:\n    url: /s\ndestinations:\n"),
+    ]);
+    let config = ConfigLoader::load(&path).unwrap().0;
+
+    assert!(config["sources"].get("s").is_some());
+    assert!(config["destinations"].get("d").is_some());
+  }
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+use serde_yaml::Value as YamlValue;
+
+/// Keys whose values are replaced as a whole instead of merged, since their fields depend on `type`.
+const REPLACED: [&str; 2] = ["auth", "body"];
+
+/// Deep-merges `overlay` onto `base`.
+///
+/// Mappings are merged key by key with `overlay` winning, except for `auth` and `body` which are
+/// replaced whole. A `null` in `overlay` removes the key; any other value replaces it.
+pub fn deep_merge(base: YamlValue, overlay: YamlValue) -> YamlValue {
+  match (base, overlay) {
+    (YamlValue::Mapping(mut base), YamlValue::Mapping(overlay)) => {
+      for (key, value) in overlay {
+        if value.is_null() {
+          base.remove(&key);
+          continue;
+        }
+
+        let merged = match base.remove(&key) {
+          Some(current) if !key.as_str().is_some_and(|key| REPLACED.contains(&key)) => deep_merge(current, value),
+          _ => value,
+        };
+        base.insert(key, merged);
+      }
+      YamlValue::Mapping(base)
+    },
+    (_, overlay) => overlay,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn yaml(content: &str) -> YamlValue {
+    serde_yaml::from_str(content).unwrap()
+  }
+
+  #[test]
+  fn merges_nested_mappings() {
+    let base = yaml("sources: {a: {url: /a, timeout: 5}, b: {url: /b}}");
+    let overlay = yaml("sources: {a: {timeout: 10}, c: {url: /c}}");
+
+    assert_eq!(
+      deep_merge(base, overlay),
+      yaml("sources: {a: {url: /a, timeout: 10}, b: {url: /b}, c: {url: /c}}"));
+  }
+
+  #[test]
+  fn replaces_scalars_and_sequences() {
+    let base = yaml("{path: /a, methods: [get, post], sources: {a: 1}}");
+    let overlay = yaml("{path: /b, methods: [put], sources: [a]}");
+
+    assert_eq!(deep_merge(base, overlay), yaml("{path: /b, methods: [put], sources: [a]}"));
+  }
+
+  #[test]
+  fn replaces_auth_and_body_whole() {
+    let base = yaml("{auth: {type: basic, username: u, password: p}, body: {type: text, text: t}, retry: {max_attempts: 2}}");
+    let overlay = yaml("{auth: {type: bearer, token: t}, body: {type: none}, retry: {jitter: false}}");
+
+    assert_eq!(
+      deep_merge(base, overlay),
+      yaml("{auth: {type: bearer, token: t}, body: {type: none}, retry: {max_attempts: 2, jitter: false}}"));
+  }
+
+  #[test]
+  fn removes_keys_set_to_null() {
+    let base = yaml("sources: {a: {url: /a, cache: {ttl: 5}}, b: {url: /b}}");
+    let overlay = yaml("sources: {a: {cache: ~}, b: ~, c: ~}");
+
+    assert_eq!(deep_merge(base, overlay), yaml("sources: {a: {url: /a}}"));
+  }
+}
\ No newline at end of file
@@ -1,10 +1,14 @@
-use std::str::FromStr;
+use std::path::Path;
 use chrono::Utc;
-use serde_yaml::Value as YamlValue;
-use serde_json::Value as JsonValue;
-use sqlx::Row;
+use serde_yaml::{Mapping, Value as YamlValue};
+use sqlx::{types::Json, Row};
 
 use self::config_file::{ConfigFile, FileType};
+use self::state::ConfigState;
+use self::interpolation::Interpolation;
+use self::loader::{ConfigLoader, Origins};
+use self::templates::Templates;
+use self::merge::deep_merge;
 
 use crate::{
   data::{
@@ -18,126 +22,287 @@ use crate::{
 };
 pub use self::error::Error;
 pub use self::yaml_parser::YamlParser;
+pub use self::watcher::watch;
+pub use self::plan::Plan;
+pub use self::versions::ConfigVersion;
+pub use self::command::run_command;
 
 mod error;
 mod yaml_parser;
 mod config_file;
+mod watcher;
+mod plan;
+mod state;
+mod versions;
+mod schema;
+mod command;
+mod interpolation;
+mod loader;
+mod merge;
+mod templates;
 
-pub async fn parse_config() -> Result<(), Error> {
-  let config = serde_yaml::from_str::<YamlValue>(
-    &ConfigFile::new(&dotenv::var("CONFIG_FILE")
-      .unwrap_or_else(|_| String::from("/etc/fusion/fusion.yaml")),
-    FileType::Config)?
-    .read()?)?;
+/// Advisory lock key held while a config is applied to the database.
+const APPLY_LOCK: i64 = 0x6675_7369_6f6e;
+
+pub fn config_path() -> String {
+  dotenv::var("CONFIG_FILE")
+    .unwrap_or_else(|_| String::from("/etc/fusion/fusion.yaml"))
+}
+
+/// Active profiles from `FUSION_PROFILE`, comma separated and applied in order.
+pub fn profiles() -> Vec<String> {
+  dotenv::var("FUSION_PROFILE")
+    .map(|profiles| profiles.split(',')
+      .map(str::trim)
+      .filter(|profile| !profile.is_empty())
+      .map(str::to_owned)
+      .collect())
+    .unwrap_or_default()
+}
+
+/// Overlay file for `profile`, next to the config file: `fusion.yaml` becomes `fusion.<profile>.yaml`.
+fn profile_path(path: &str, profile: &str) -> String {
+  let path = Path::new(path);
+  let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+  let name = match path.extension() {
+    Some(extension) => format!("{}.{}.{}", stem, profile, extension.to_string_lossy()),
+    None => format!("{}.{}", stem, profile),
+  };
+
+  path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Paths of the config files and included directories, and every `filter_file`, `fallback_file`
+/// and secret file they reference.
+pub fn watched_files() -> Result<Vec<String>, Error> {
+  let (config, files, _) = read_config()?;
+
+  Ok(files.into_iter()
+    .chain(Interpolation::files(&config)?)
+    .chain(referenced_files(&Interpolation::resolve(config)?)?.into_iter().map(|(path, _)| path))
+    .collect())
+}
+
+/// Reads the config file and its includes, checking each against the schema before merging,
+/// deep-merges the active profile overlays onto it and expands templates.
+///
+/// Returns the config with every file and directory read and where its entries were declared.
+/// Placeholders are left unresolved; see [`Interpolation`].
+fn read_config() -> Result<(YamlValue, Vec<String>, Origins), Error> {
+  let path = config_path();
+  let (mut config, mut files, mut origins) = ConfigLoader::load(&path)?;
+
+  for profile in profiles() {
+    let (overlay, overlay_files, overlay_origins) = ConfigLoader::load(&profile_path(&path, &profile))?;
+    config = deep_merge(config, overlay);
+    files.extend(overlay_files);
+    origins.extend(overlay_origins);
+  }
+
+  Ok((Templates::expand(config)?, files, origins))
+}
+
+/// Applies the config file if its hash differs from the latest version applied from it, returning whether it did.
+pub async fn parse_config() -> Result<bool, Error> {
+  let (config, _, origins) = read_config()?;
+  let profile = Some(profiles().join(",")).filter(|profile| !profile.is_empty());
 
   let mut conn = get_conn().await?;
 
   let prev_config_ver = sqlx::query("
       SELECT config_versions.hash
       FROM config_versions
+      WHERE config_versions.rollback_of IS NULL
       ORDER BY config_versions.updated_on DESC
       LIMIT 1;
     ")
     .fetch_optional(&mut conn)
     .await?;
 
-  let result = Hasher::hash_string(serde_yaml::to_string(&config)?);
+  let result = hash_config(&Interpolation::resolve(config.clone())?, profile.as_deref())?;
 
   match prev_config_ver {
     Some(row) => {
-      if row.try_get::<Vec<u8>, _>("hash")? != result {
-        println!("Configuration changed, updating database.");
-        update_config(config, result).await?;
+      if row.try_get::<Vec<u8>, _>("hash")? == result {
+        return Ok(false);
       }
+      println!("Configuration changed, updating database.");
     },
-    None => {
-      println!("No previous configuration found, initializing database.");
-      update_config(config, result).await?;
-    },
+    None => println!("No previous configuration found, initializing database."),
   }
 
-  Ok(())
+  update_config(config, &origins, result, profile.as_deref(), None).await?;
+
+  Ok(true)
 }
 
-async fn update_config(config: YamlValue, hash: Vec<u8>) -> Result<(), Error> {
-  let config = config.as_mapping().ok_or(Error::Str("Configuration invalid."))?;
-  let mut tran = get_tran().await?;
+/// Computes what applying the config file would change, without writing anything.
+pub async fn plan_config() -> Result<Plan, Error> {
+  let (config, _, origins) = read_config()?;
+  let config = normalize_config(config)?;
+
+  Plan::compare(
+    &ConfigState::load(&mut get_conn().await?).await?,
+    &ConfigState::read(&Interpolation::resolve(config)?, &origins)?)
+}
+
+/// Hashes the merged config together with the files it references and the active profile, so
+/// edits to those files or a profile switch count as changes.
+fn hash_config(config: &YamlValue, profile: Option<&str>) -> Result<Vec<u8>, Error> {
+  let mut content = serde_yaml::to_string(config)?;
+
+  if let Some(profile) = profile {
+    content.push_str(profile);
+  }
+
+  for (path, file_type) in referenced_files(config)? {
+    content.push_str(&path);
+    content.push_str(&ConfigFile::new(&path, file_type)?.read()?);
+  }
+
+  Ok(Hasher::hash_string(content))
+}
+
+fn referenced_files(config: &YamlValue) -> Result<Vec<(String, FileType)>, Error> {
+  let mut files = Vec::new();
 
   if let Some(YamlValue::Mapping(sources)) = config.get("sources") {
-    for (code, data) in sources {
-      Source {
-        id: None,
-        code: YamlParser::to_string(code)?,
-        url: YamlParser::to_string_req(data, "url")?,
-        params: YamlParser::to_hashmap_option(data.get("params"))?.unwrap_or_default(),
-        headers: YamlParser::to_hashmap_option(data.get("headers"))?.unwrap_or_default(),
-        timeout: YamlParser::to_duration(data.get("timeout"))?,
-        auth: data.get("auth").try_into()?,
-        body: data.get("body").try_into()?,
-        fallback: if let Some(path) = YamlParser::to_string_option(data.get("fallback_file"))? {
-          Some(ConfigFile::new(&path, FileType::Fallback)?.read()?)
-        } else {
-          YamlParser::to_string_option_multiline(data.get("fallback"))?
-        }.map(|val| JsonValue::from_str(&val)).transpose()?,
+    for data in sources.values() {
+      if let Some(path) = YamlParser::to_string_option(data.get("fallback_file"))? {
+        files.push((path, FileType::Fallback));
       }
-      .insert_or_update(&mut tran).await?;
     }
   }
 
   if let Some(YamlValue::Mapping(destinations)) = config.get("destinations") {
-    for (code, data) in destinations {
-      let dest = Destination {
-        id: None,
-        code: YamlParser::to_string(code)?,
-        path: YamlParser::to_string_req(data, "path")?,
-        is_active: YamlParser::to_bool_option(data.get("is_active"))?.unwrap_or_default(),
-        headers: YamlParser::to_hashmap_option(data.get("headers"))?.unwrap_or_default(),
-        is_auth: YamlParser::to_bool_option(data.get("is_auth"))?.unwrap_or_default(),
-        filter: if let Some(path) = YamlParser::to_string_option(data.get("filter_file"))? {
-          Some(ConfigFile::new(&path, FileType::Filter)?.read()?)
-        } else {
-          YamlParser::to_string_option_multiline(data.get("filter"))?
-        },
+    for data in destinations.values() {
+      if let Some(path) = YamlParser::to_string_option(data.get("filter_file"))? {
+        files.push((path, FileType::Filter));
+      }
+    }
+  }
+
+  Ok(files)
+}
+
+/// Inlines `filter_file` and `fallback_file` contents so the config is self-contained for snapshots.
+///
+/// Placeholders are kept, and the inlined contents escaped so they are not read as placeholders.
+fn normalize_config(mut config: YamlValue) -> Result<YamlValue, Error> {
+  if let Some(YamlValue::Mapping(sources)) = config.get_mut("sources") {
+    for data in sources.values_mut() {
+      if let Some(path) = YamlParser::to_string_option(data.get("fallback_file"))? {
+        let fallback = ConfigFile::new(&Interpolation::resolve_str(&path)?, FileType::Fallback)?.read()?;
+        if let YamlValue::Mapping(data) = data {
+          data.remove("fallback_file");
+          data.insert(YamlValue::from("fallback"), YamlValue::from(Interpolation::escape(&fallback)));
+        }
       }
-      .insert_or_update(&mut tran).await?;
+    }
+  }
 
-      if let Some(YamlValue::Sequence(dest_sources)) = data.get("sources") {
-        dest.unlink_sources(&mut tran).await?;
-        dest.link_sources(YamlParser::vec_to_string(dest_sources)?, &mut tran).await?;
+  if let Some(YamlValue::Mapping(destinations)) = config.get_mut("destinations") {
+    for data in destinations.values_mut() {
+      if let Some(path) = YamlParser::to_string_option(data.get("filter_file"))? {
+        let filter = ConfigFile::new(&Interpolation::resolve_str(&path)?, FileType::Filter)?.read()?;
+        if let YamlValue::Mapping(data) = data {
+          data.remove("filter_file");
+          data.insert(YamlValue::from("filter"), YamlValue::from(Interpolation::escape(&filter)));
+        }
       }
     }
   }
 
-  if let Some(YamlValue::Sequence(auth_tokens)) = config.get("auth_tokens") {
-    for value in auth_tokens {
-      let token = match value {
-        YamlValue::String(val) => AuthToken {
-          id: None,
-          value: Hasher::hash_string(val.to_owned()),
-          expiration: None,
-        },
-        YamlValue::Mapping(_) => AuthToken {
-          id: None,
-          value: Hasher::hash_string(YamlParser::to_string_req(value, "value")?),
-          expiration: YamlParser::to_datetime_option(value.get("expiration"))?,
-        },
-        _ => Err(Error::Str("`Value` could not be converted to `AuthToken`"))?
+  Ok(config)
+}
+
+/// Replaces auth token values with the hashes of their resolved values, which is all the database
+/// keeps of them.
+fn redact_tokens(mut config: YamlValue) -> Result<YamlValue, Error> {
+  if let Some(YamlValue::Sequence(tokens)) = config.get_mut("auth_tokens") {
+    for token in tokens {
+      if let YamlValue::String(value) = token {
+        *token = YamlValue::Mapping(Mapping::from_iter([(YamlValue::from("value"), YamlValue::from(value.as_str()))]));
       }
-      .insert_or_update(&mut tran).await?;
-      
-      if let Some(YamlValue::Sequence(token_dests)) = value.get("destinations") {
-        token.unlink_destinations(&mut tran).await?;
-        token.link_destinations(YamlParser::vec_to_string(token_dests)?, &mut tran).await?;
+      if let YamlValue::Mapping(entry) = token {
+        if let Some(value) = entry.remove("value") {
+          let hash = Hasher::hash_string(YamlParser::to_string(&Interpolation::resolve(value)?)?);
+          entry.insert(YamlValue::from("value_hash"), YamlValue::from(Hasher::to_hex(&hash)));
+        }
       }
     }
   }
 
+  Ok(config)
+}
+
+/// Applies `config` with its placeholders resolved, keeping them unresolved in the stored snapshot
+/// so secrets are not written to it. Validation errors point into the files `origins` names.
+async fn update_config(config: YamlValue, origins: &Origins, hash: Vec<u8>, profile: Option<&str>, rollback_of: Option<&str>) -> Result<(), Error> {
+  let config = normalize_config(config)?;
+  let state = ConfigState::read(&Interpolation::resolve(config.clone())?, origins)?;
+  let mut tran = get_tran().await?;
+
+  // Serializes applies across the watcher, admin rollbacks and other processes until commit.
+  sqlx::query("SELECT pg_advisory_xact_lock($1)")
+    .bind(APPLY_LOCK)
+    .execute(&mut *tran)
+    .await?;
+
+  let mut source_codes = Vec::<String>::new();
+  let mut destination_codes = Vec::<String>::new();
+  let mut token_values = Vec::<Vec<u8>>::new();
+
+  for source in state.sources {
+    let source = source.insert_or_update(&mut tran).await?;
+    source_codes.push(source.code);
+  }
+
+  for (dest, dest_sources) in state.destinations {
+    let dest = dest.insert_or_update(&mut tran).await?;
+
+    dest.unlink_sources(&mut tran).await?;
+    if !dest_sources.is_empty() {
+      dest.link_sources(dest_sources, &mut tran).await?;
+    }
+
+    destination_codes.push(dest.code);
+  }
+
+  for (token, token_dests) in state.auth_tokens {
+    let token = token.insert_or_update(&mut tran).await?;
+
+    token.unlink_destinations(&mut tran).await?;
+    if !token_dests.is_empty() {
+      token.link_destinations(token_dests, &mut tran).await?;
+    }
+
+    token_values.push(token.value);
+  }
+
+  let removed_destinations = Destination::delete_except(&destination_codes, &mut tran).await?;
+  let removed_sources = Source::delete_except(&source_codes, &mut tran).await?;
+  let removed_tokens = AuthToken::delete_except(&token_values, &mut tran).await?;
+
+  if !removed_destinations.is_empty() {
+    println!("Removed destinations: {}", removed_destinations.join(", "));
+  }
+  if !removed_sources.is_empty() {
+    println!("Removed sources: {}", removed_sources.join(", "));
+  }
+  if !removed_tokens.is_empty() {
+    println!("Removed {} auth tokens.", removed_tokens.len());
+  }
+
   sqlx::query("
-    INSERT INTO config_versions (updated_on, hash)
-    VALUES ($1, $2)
+    INSERT INTO config_versions (updated_on, hash, config, profile, rollback_of)
+    VALUES ($1, $2, $3, $4, $5::uuid)
   ")
   .bind(Utc::now())
   .bind(hash)
+  .bind(Json(serde_json::to_value(redact_tokens(config)?)?))
+  .bind(profile)
+  .bind(rollback_of)
   .execute(&mut *tran)
   .await?;
 
@@ -0,0 +1,211 @@
+use std::{
+  collections::{BTreeMap, BTreeSet, HashSet},
+  fmt::{self, Display, Formatter},
+};
+
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use crate::utils::Hasher;
+
+use super::{ConfigState, Error};
+
+#[derive(Serialize, Default)]
+pub struct Changes {
+  added: Vec<String>,
+  /// Changed entries, with the names of the fields that differ.
+  changed: BTreeMap<String, Vec<String>>,
+  removed: Vec<String>,
+}
+
+impl Changes {
+  fn compare(current: BTreeMap<String, JsonValue>, desired: BTreeMap<String, JsonValue>) -> Self {
+    let mut changes = Self::default();
+
+    for (key, value) in &desired {
+      match current.get(key) {
+        None => changes.added.push(key.clone()),
+        Some(current) if current != value => {
+          let fields = match (current, value) {
+            (JsonValue::Object(current), JsonValue::Object(value)) => value.keys()
+              .chain(current.keys())
+              .collect::<BTreeSet<_>>()
+              .into_iter()
+              .filter(|field| current.get(*field) != value.get(*field))
+              .cloned()
+              .collect(),
+            _ => Vec::new(),
+          };
+          changes.changed.insert(key.clone(), fields);
+        },
+        Some(_) => {},
+      }
+    }
+
+    changes.removed = current.into_keys()
+      .filter(|key| !desired.contains_key(key))
+      .collect();
+
+    changes
+  }
+
+  fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+  }
+}
+
+#[derive(Serialize, Default)]
+pub struct LinkChanges {
+  added: Vec<(String, String)>,
+  removed: Vec<(String, String)>,
+}
+
+impl LinkChanges {
+  fn compare(current: BTreeSet<(String, String)>, desired: BTreeSet<(String, String)>) -> Self {
+    Self {
+      added: desired.difference(&current).cloned().collect(),
+      removed: current.difference(&desired).cloned().collect(),
+    }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty()
+  }
+}
+
+/// Difference between two config states, such as the database and the config file.
+#[derive(Serialize)]
+pub struct Plan {
+  sources: Changes,
+  destinations: Changes,
+  source_links: LinkChanges,
+  auth_tokens: Changes,
+  auth_token_links: LinkChanges,
+}
+
+impl Plan {
+  pub(super) fn compare(current: &ConfigState, desired: &ConfigState) -> Result<Self, Error> {
+    Ok(Self {
+      sources: Changes::compare(Self::source_fields(current)?, Self::source_fields(desired)?),
+      destinations: Changes::compare(Self::destination_fields(current)?, Self::destination_fields(desired)?),
+      source_links: LinkChanges::compare(Self::source_links(current), Self::source_links(desired)),
+      auth_tokens: Changes::compare(Self::token_fields(current), Self::token_fields(desired)),
+      auth_token_links: LinkChanges::compare(Self::token_links(current), Self::token_links(desired)),
+    })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.sources.is_empty()
+      && self.destinations.is_empty()
+      && self.source_links.is_empty()
+      && self.auth_tokens.is_empty()
+      && self.auth_token_links.is_empty()
+  }
+
+  fn fields(value: &impl Serialize) -> Result<JsonValue, Error> {
+    let mut value = serde_json::to_value(value)?;
+    if let Some(object) = value.as_object_mut() {
+      object.remove("id");
+    }
+    Ok(value)
+  }
+
+  fn source_fields(state: &ConfigState) -> Result<BTreeMap<String, JsonValue>, Error> {
+    state.sources.iter()
+      .map(|source| Ok((source.code.clone(), Self::fields(source)?)))
+      .collect()
+  }
+
+  fn destination_fields(state: &ConfigState) -> Result<BTreeMap<String, JsonValue>, Error> {
+    state.destinations.iter()
+      .map(|(destination, _)| Ok((destination.code.clone(), Self::fields(destination)?)))
+      .collect()
+  }
+
+  fn token_fields(state: &ConfigState) -> BTreeMap<String, JsonValue> {
+    state.auth_tokens.iter()
+      .map(|(token, _)| (Self::token_name(&token.value),
+        json!({ "expiration": token.expiration.map(|expiration| expiration.to_rfc3339()) })))
+      .collect()
+  }
+
+  /// Links to undeclared sources are never written, so they are left out. Optional sources are
+  /// marked, so that toggling `required` shows as the link being replaced.
+  fn source_links(state: &ConfigState) -> BTreeSet<(String, String)> {
+    let declared = state.sources.iter()
+      .map(|source| source.code.as_str())
+      .collect::<HashSet<_>>();
+
+    state.destinations.iter()
+      .flat_map(|(destination, links)| links.iter()
+        .filter(|(code, _)| declared.contains(code.as_str()))
+        .map(|(code, required)| (destination.code.clone(), match required {
+          true => code.clone(),
+          false => format!("{} (optional)", code),
+        })))
+      .collect()
+  }
+
+  fn token_links(state: &ConfigState) -> BTreeSet<(String, String)> {
+    let declared = state.destinations.iter()
+      .map(|(destination, _)| destination.code.as_str())
+      .collect::<HashSet<_>>();
+
+    state.auth_tokens.iter()
+      .flat_map(|(token, codes)| codes.iter()
+        .filter(|code| declared.contains(code.as_str()))
+        .map(|code| (Self::token_name(&token.value), code.clone())))
+      .collect()
+  }
+
+  /// Tokens are stored hashed, so they are identified by a prefix of the hash.
+  fn token_name(value: &[u8]) -> String {
+    Hasher::to_hex(&value[..value.len().min(6)])
+  }
+
+  fn write_changes(f: &mut Formatter<'_>, title: &str, changes: &Changes) -> fmt::Result {
+    if changes.is_empty() {
+      return Ok(());
+    }
+
+    writeln!(f, "{}:", title)?;
+    for key in &changes.added {
+      writeln!(f, "  + {}", key)?;
+    }
+    for (key, fields) in &changes.changed {
+      writeln!(f, "  ~ {} ({})", key, fields.join(", "))?;
+    }
+    for key in &changes.removed {
+      writeln!(f, "  - {}", key)?;
+    }
+    Ok(())
+  }
+
+  fn write_links(f: &mut Formatter<'_>, title: &str, links: &LinkChanges) -> fmt::Result {
+    if links.is_empty() {
+      return Ok(());
+    }
+
+    writeln!(f, "{}:", title)?;
+    for (from, to) in &links.added {
+      writeln!(f, "  + {} -> {}", from, to)?;
+    }
+    for (from, to) in &links.removed {
+      writeln!(f, "  - {} -> {}", from, to)?;
+    }
+    Ok(())
+  }
+}
+
+impl Display for Plan {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    if self.is_empty() {
+      return writeln!(f, "No changes.");
+    }
+
+    Self::write_changes(f, "Sources", &self.sources)?;
+    Self::write_changes(f, "Destinations", &self.destinations)?;
+    Self::write_links(f, "Destination sources", &self.source_links)?;
+    Self::write_changes(f, "Auth tokens", &self.auth_tokens)?;
+    Self::write_links(f, "Auth token destinations", &self.auth_token_links)
+  }
+}
\ No newline at end of file
@@ -0,0 +1,861 @@
+//! Typed shape of the config. Each file is checked against it before merging, so misspelled
+//! keys and wrong value types are reported with their location, and the merged config is read
+//! into it again to build sources, destinations and tokens.
+
+use std::{cell::Cell, collections::HashMap, fmt::{self, Formatter}, marker::PhantomData, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{
+  de::{
+    self,
+    value::{BoolDeserializer, F64Deserializer, I64Deserializer, MapAccessDeserializer, StrDeserializer, U64Deserializer},
+    DeserializeOwned, MapAccess, SeqAccess, Unexpected, Visitor,
+  },
+  Deserialize, Deserializer,
+};
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+
+use crate::{
+  data::{
+    models::{AuthToken, Destination, Source},
+    types::{retry::RetryError, Auth, Body, Cache, CircuitBreaker, Forward, ListFormat, Method, MultiValue, Retry},
+  },
+  utils::Hasher,
+};
+
+use super::{config_file::{ConfigFile, FileType}, interpolation::Interpolation, Error, YamlParser};
+
+thread_local! {
+  /// Set while a config file is checked, before its placeholders are resolved.
+  static UNRESOLVED: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a config mapping")]
+pub struct ConfigSchema {
+  /// Followed by `ConfigLoader`, which leaves it out of the merged config.
+  #[serde(default, deserialize_with = "check")]
+  include: PhantomData<Option<Vec<String>>>,
+  /// Expanded into the entries that extend them and removed before the config is built.
+  #[serde(default, deserialize_with = "check")]
+  templates: PhantomData<Option<HashMap<String, TemplateSchema>>>,
+  pub(super) sources: Option<Entries<SourceSchema>>,
+  pub(super) destinations: Option<Entries<DestinationSchema>>,
+  pub(super) auth_tokens: Option<Vec<AuthTokenSchema>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a source mapping")]
+pub(super) struct SourceSchema {
+  /// Expanded before the config is built.
+  #[serde(default, deserialize_with = "check")]
+  extends: PhantomData<Option<TemplateNames>>,
+  method: Option<Interpolated<MethodName>>,
+  url: Option<String>,
+  params: Option<HashMap<String, MultiValueSchema>>,
+  list_format: Option<Interpolated<ListFormatName>>,
+  headers: Option<HashMap<String, MultiValueSchema>>,
+  forward: Option<ForwardSchema>,
+  timeout: Option<Interpolated<DurationSchema>>,
+  retry: Option<RetrySchema>,
+  circuit_breaker: Option<CircuitBreakerSchema>,
+  cache: Option<CacheSchema>,
+  auth: Option<Typed<AuthSchema>>,
+  body: Option<Typed<BodySchema>>,
+  fallback: Option<Interpolated<JsonString>>,
+  fallback_file: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a destination mapping")]
+pub(super) struct DestinationSchema {
+  /// Expanded before the config is built.
+  #[serde(default, deserialize_with = "check")]
+  extends: PhantomData<Option<TemplateNames>>,
+  path: Option<String>,
+  methods: Option<Vec<Interpolated<MethodName>>>,
+  is_active: Option<Interpolated<bool>>,
+  headers: Option<HashMap<String, MultiValueSchema>>,
+  is_auth: Option<Interpolated<bool>>,
+  filter: Option<String>,
+  filter_file: Option<String>,
+  cache: Option<DestinationCacheSchema>,
+  deadline: Option<Interpolated<DurationSchema>>,
+  sources: Option<Vec<SourceLinkSchema>>,
+}
+
+/// Fields shared through `extends:`, from either a source or a destination.
+///
+/// Only checked, since templates are expanded before the config is built.
+struct TemplateSchema;
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a source code or a mapping with `source` and `required`")]
+enum SourceLinkSchema {
+  Code(String),
+  Link(SourceLinkEntrySchema),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceLinkEntrySchema {
+  source: String,
+  required: Option<Interpolated<bool>>,
+}
+
+pub(super) enum AuthTokenSchema {
+  Value(String),
+  Entry(AuthTokenEntrySchema),
+}
+
+/// A token given by `value`, or by the `value_hash` that config snapshots store in its place.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "an auth token mapping")]
+pub(super) struct AuthTokenEntrySchema {
+  value: Option<String>,
+  value_hash: Option<TokenHash>,
+  expiration: Option<Interpolated<DateTimeSchema>>,
+  destinations: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a mapping with `query` and/or `headers` lists")]
+struct ForwardSchema {
+  query: Option<Vec<ForwardEntry>>,
+  headers: Option<Vec<ForwardEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a retry mapping")]
+struct RetrySchema {
+  max_attempts: Option<Interpolated<u32>>,
+  base_backoff: Option<Interpolated<DurationSchema>>,
+  max_backoff: Option<Interpolated<DurationSchema>>,
+  jitter: Option<Interpolated<bool>>,
+  statuses: Option<Vec<Interpolated<u16>>>,
+  errors: Option<Vec<Interpolated<RetryErrorName>>>,
+  retry_after: Option<Interpolated<bool>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a circuit breaker mapping")]
+struct CircuitBreakerSchema {
+  failure_threshold: Option<Interpolated<Count>>,
+  open_duration: Option<Interpolated<DurationSchema>>,
+  half_open_probes: Option<Interpolated<Count>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a cache mapping")]
+struct CacheSchema {
+  ttl: Option<Interpolated<DurationSchema>>,
+  max_entries: Option<Interpolated<usize>>,
+  max_bytes: Option<Interpolated<usize>>,
+  cache_control: Option<Interpolated<bool>>,
+  stale_while_revalidate: Option<Interpolated<DurationSchema>>,
+  stale_if_error: Option<Interpolated<DurationSchema>>,
+}
+
+/// Destination results are cached by TTL only, without the source cache's stale serving or
+/// `Cache-Control` handling.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, expecting = "a destination cache mapping")]
+struct DestinationCacheSchema {
+  ttl: Option<Interpolated<DurationSchema>>,
+  max_entries: Option<Interpolated<usize>>,
+  max_bytes: Option<Interpolated<usize>>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields, expecting = "an auth mapping with `type`")]
+enum AuthSchema {
+  None {},
+  Basic { username: String, password: String },
+  Bearer { token: String },
+  Param { key: String, value: String },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields, expecting = "a body mapping with `type`")]
+enum BodySchema {
+  None {},
+  Text { text: String },
+  Json { json: Interpolated<JsonString> },
+  Form { form: Option<HashMap<String, ScalarSchema>> },
+  Multi { form: Option<HashMap<String, ScalarSchema>> },
+  Passthrough {},
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a string, number or boolean, or a list of them")]
+enum MultiValueSchema {
+  One(ScalarSchema),
+  Many(Vec<ScalarSchema>),
+}
+
+/// Mapping entries in the order they are written.
+pub(super) struct Entries<T>(pub(super) Vec<(String, T)>);
+
+/// A typed value, which may also be a `${...}` placeholder or the string it resolves to, such as
+/// `"5"` or `"true"`. Placeholders pass the check of the config files without a value, and are
+/// read once resolved.
+struct Interpolated<T>(Option<T>);
+
+/// An `auth` or `body` mapping, of type `none` when it leaves `type` out.
+struct Typed<T>(T);
+
+/// A string, number or boolean, kept as written.
+struct ScalarSchema(String);
+
+struct MethodName(Method);
+
+struct ListFormatName(ListFormat);
+
+struct RetryErrorName(RetryError);
+
+/// A whole number of at least 1.
+struct Count(u32);
+
+/// Seconds as a number, or a number with a unit such as `250ms`, `1.5s` or `2m`.
+struct DurationSchema(Duration);
+
+/// A JSON document written as a string.
+struct JsonString(JsonValue);
+
+struct DateTimeSchema(DateTime<Utc>);
+
+/// A hex encoded SHA-256 digest.
+struct TokenHash(Vec<u8>);
+
+/// Inbound name forwarded as is, or a single `from: to` mapping that renames it.
+struct ForwardEntry(String, String);
+
+/// A template name or a list of them.
+struct TemplateNames;
+
+/// Checks a value that is read elsewhere, such as `include` and `extends`, and drops it.
+fn check<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<PhantomData<T>, D::Error> {
+  T::deserialize(deserializer).map(|_| PhantomData)
+}
+
+/// Reads a string through `parse`, inside the visitor so that errors point at the value itself.
+fn parse_str<'de, D: Deserializer<'de>, T>(deserializer: D, expecting: &'static str, parse: impl FnOnce(&str) -> Result<T, String>) -> Result<T, D::Error> {
+  struct StrVisitor<F>(&'static str, F);
+
+  impl<'de, T, F: FnOnce(&str) -> Result<T, String>> Visitor<'de> for StrVisitor<F> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+      f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+      (self.1)(value).map_err(E::custom)
+    }
+  }
+
+  deserializer.deserialize_str(StrVisitor(expecting, parse))
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Entries<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct EntriesVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for EntriesVisitor<T> {
+      type Value = Entries<T>;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a mapping of codes")
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+          entries.push(entry);
+        }
+        Ok(Entries(entries))
+      }
+    }
+
+    deserializer.deserialize_map(EntriesVisitor(PhantomData))
+  }
+}
+
+impl<T> Default for Entries<T> {
+  fn default() -> Self {
+    Self(Vec::new())
+  }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Interpolated<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct InterpolatedVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: DeserializeOwned> Visitor<'de> for InterpolatedVisitor<T> {
+      type Value = Interpolated<T>;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a value or a `${...}` placeholder")
+      }
+
+      fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        T::deserialize(BoolDeserializer::new(value)).map(|value| Interpolated(Some(value)))
+      }
+
+      fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        T::deserialize(I64Deserializer::new(value)).map(|value| Interpolated(Some(value)))
+      }
+
+      fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        T::deserialize(U64Deserializer::new(value)).map(|value| Interpolated(Some(value)))
+      }
+
+      fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        T::deserialize(F64Deserializer::new(value)).map(|value| Interpolated(Some(value)))
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        if UNRESOLVED.get() && Interpolation::is_unresolved(value) {
+          return Ok(Interpolated(None));
+        }
+
+        T::deserialize(StrDeserializer::<E>::new(value))
+          .or_else(|error| match serde_yaml::from_str(value) {
+            Ok(scalar @ (YamlValue::Bool(_) | YamlValue::Number(_))) => T::deserialize(scalar).map_err(E::custom),
+            _ => Err(error),
+          })
+          .map(|value| Interpolated(Some(value)))
+      }
+    }
+
+    deserializer.deserialize_any(InterpolatedVisitor(PhantomData))
+  }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Typed<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let mut value = YamlValue::deserialize(deserializer)?;
+    let untyped = match &mut value {
+      YamlValue::Mapping(mapping) if !mapping.contains_key("type") => {
+        mapping.insert(YamlValue::from("type"), YamlValue::from("none"));
+        true
+      },
+      _ => false,
+    };
+
+    T::deserialize(value).map(Self).map_err(|error| if untyped {
+      de::Error::custom(format!("{} (`type` is not set, so it is `none`)", error))
+    } else {
+      de::Error::custom(error)
+    })
+  }
+}
+
+impl<'de> Deserialize<'de> for ScalarSchema {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    YamlParser::to_scalar_string(&YamlValue::deserialize(deserializer)?)
+      .map(Self)
+      .map_err(|_| de::Error::custom("expected a string, number or boolean"))
+  }
+}
+
+impl<'de> Deserialize<'de> for MethodName {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    parse_str(deserializer, "a method name", |name| Method::from_name(name)
+      .map(Self)
+      .ok_or_else(|| format!("method `{}` invalid", name)))
+  }
+}
+
+impl<'de> Deserialize<'de> for ListFormatName {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    parse_str(deserializer, "a list format", |name| ListFormat::from_name(name)
+      .map(Self)
+      .ok_or_else(|| format!("list format `{}` invalid, expected `repeat` or `comma`", name)))
+  }
+}
+
+impl<'de> Deserialize<'de> for RetryErrorName {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    parse_str(deserializer, "a retry error kind", |name| RetryError::from_name(name)
+      .map(Self)
+      .ok_or_else(|| format!("retry error kind `{}` invalid", name)))
+  }
+}
+
+impl<'de> Deserialize<'de> for Count {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    match u32::deserialize(deserializer)? {
+      0 => Err(de::Error::invalid_value(Unexpected::Unsigned(0), &"a number of at least 1")),
+      count => Ok(Self(count)),
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for DurationSchema {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct DurationVisitor;
+
+    impl<'de> Visitor<'de> for DurationVisitor {
+      type Value = DurationSchema;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a number of seconds or a duration such as `250ms`, `1.5s` or `2m`")
+      }
+
+      fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(DurationSchema(Duration::from_secs(value)))
+      }
+
+      fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        YamlParser::from_secs(value)
+          .map(DurationSchema)
+          .ok_or_else(|| de::Error::custom(format!("duration `{}` invalid", value)))
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        YamlParser::parse_duration(value)
+          .map(DurationSchema)
+          .ok_or_else(|| de::Error::custom(format!("duration `{}` invalid, expected a unit of `ms`, `s`, `m` or `h`", value)))
+      }
+    }
+
+    deserializer.deserialize_any(DurationVisitor)
+  }
+}
+
+impl<'de> Deserialize<'de> for JsonString {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    parse_str(deserializer, "a JSON string", |value| JsonValue::from_str(value)
+      .map(Self)
+      .map_err(|error| format!("invalid JSON: {}", error)))
+  }
+}
+
+impl<'de> Deserialize<'de> for DateTimeSchema {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    parse_str(deserializer, "an RFC 3339 date time", |value| DateTime::from_str(value)
+      .map(Self)
+      .map_err(|error| format!("date time `{}` invalid: {}", value, error)))
+  }
+}
+
+impl<'de> Deserialize<'de> for TokenHash {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    parse_str(deserializer, "a hex encoded SHA-256 digest", |value| Hasher::from_hex(value)
+      .filter(|hash| hash.len() == 32)
+      .map(Self)
+      .ok_or_else(|| String::from("expected a hex encoded SHA-256 digest")))
+  }
+}
+
+impl<'de> Deserialize<'de> for ForwardEntry {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct ForwardEntryVisitor;
+
+    impl<'de> Visitor<'de> for ForwardEntryVisitor {
+      type Value = ForwardEntry;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a name or a single `from: to` mapping")
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(ForwardEntry(value.to_owned(), value.to_owned()))
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        match (map.next_entry::<String, String>()?, map.next_key::<String>()?) {
+          (Some((from, to)), None) => Ok(ForwardEntry(from, to)),
+          _ => Err(de::Error::invalid_length(2, &self)),
+        }
+      }
+    }
+
+    deserializer.deserialize_any(ForwardEntryVisitor)
+  }
+}
+
+impl<'de> Deserialize<'de> for TemplateNames {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct TemplateNamesVisitor;
+
+    impl<'de> Visitor<'de> for TemplateNamesVisitor {
+      type Value = TemplateNames;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a template name or a list of template names")
+      }
+
+      fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(TemplateNames)
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while seq.next_element::<String>()?.is_some() {}
+        Ok(TemplateNames)
+      }
+    }
+
+    deserializer.deserialize_any(TemplateNamesVisitor)
+  }
+}
+
+impl<'de> Deserialize<'de> for TemplateSchema {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    const FIELDS: &[&str] = &[
+      "extends", "method", "url", "params", "list_format", "headers", "forward", "timeout", "retry",
+      "circuit_breaker", "cache", "auth", "body", "fallback", "fallback_file", "path", "methods",
+      "is_active", "is_auth", "filter", "filter_file", "deadline", "sources",
+    ];
+
+    struct TemplateVisitor;
+
+    impl<'de> Visitor<'de> for TemplateVisitor {
+      type Value = TemplateSchema;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a template mapping")
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+          match key.as_str() {
+            "extends" => map.next_value::<Option<TemplateNames>>().map(drop),
+            "method" => map.next_value::<Option<Interpolated<MethodName>>>().map(drop),
+            "url" | "fallback_file" | "path" | "filter" | "filter_file" => map.next_value::<Option<String>>().map(drop),
+            "params" | "headers" => map.next_value::<Option<HashMap<String, MultiValueSchema>>>().map(drop),
+            "list_format" => map.next_value::<Option<Interpolated<ListFormatName>>>().map(drop),
+            "forward" => map.next_value::<Option<ForwardSchema>>().map(drop),
+            "timeout" | "deadline" => map.next_value::<Option<Interpolated<DurationSchema>>>().map(drop),
+            "retry" => map.next_value::<Option<RetrySchema>>().map(drop),
+            "circuit_breaker" => map.next_value::<Option<CircuitBreakerSchema>>().map(drop),
+            "cache" => map.next_value::<Option<CacheSchema>>().map(drop),
+            "auth" => map.next_value::<Option<Typed<AuthSchema>>>().map(drop),
+            "body" => map.next_value::<Option<Typed<BodySchema>>>().map(drop),
+            "fallback" => map.next_value::<Option<Interpolated<JsonString>>>().map(drop),
+            "methods" => map.next_value::<Option<Vec<Interpolated<MethodName>>>>().map(drop),
+            "is_active" | "is_auth" => map.next_value::<Option<Interpolated<bool>>>().map(drop),
+            "sources" => map.next_value::<Option<Vec<SourceLinkSchema>>>().map(drop),
+            _ => Err(de::Error::unknown_field(&key, FIELDS)),
+          }?;
+        }
+        Ok(TemplateSchema)
+      }
+    }
+
+    deserializer.deserialize_map(TemplateVisitor)
+  }
+}
+
+impl<'de> Deserialize<'de> for AuthTokenSchema {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct AuthTokenVisitor;
+
+    impl<'de> Visitor<'de> for AuthTokenVisitor {
+      type Value = AuthTokenSchema;
+
+      fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a token string or a mapping with `value`")
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(AuthTokenSchema::Value(value.to_owned()))
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let entry = AuthTokenEntrySchema::deserialize(MapAccessDeserializer::new(map))?;
+        match (&entry.value, &entry.value_hash) {
+          (Some(_), None) | (None, Some(_)) => Ok(AuthTokenSchema::Entry(entry)),
+          _ => Err(de::Error::custom("expected exactly one of `value` or `value_hash`")),
+        }
+      }
+    }
+
+    deserializer.deserialize_any(AuthTokenVisitor)
+  }
+}
+
+impl ConfigSchema {
+  /// Checks `content` read from `file`, reporting the first mismatch as `file:line:column: `path`: message`.
+  ///
+  /// Placeholders in typed values are not checked until they are resolved.
+  pub fn validate(file: &str, content: &str) -> Result<(), Error> {
+    UNRESOLVED.set(true);
+    let result = serde_path_to_error::deserialize::<_, Self>(serde_yaml::Deserializer::from_str(content));
+    UNRESOLVED.set(false);
+
+    result
+      .map(|_| ())
+      .map_err(|error| Self::error(Some(file), error))
+  }
+
+  /// Reads the merged config, reporting the first mismatch as `` `path`: message ``.
+  pub(super) fn read(config: &YamlValue) -> Result<Self, Error> {
+    serde_path_to_error::deserialize::<_, Self>(config)
+      .map_err(|error| Self::error(None, error))
+  }
+
+  fn error(file: Option<&str>, error: serde_path_to_error::Error<serde_yaml::Error>) -> Error {
+    let path = error.path().to_string();
+    let location = error.inner().location();
+    let message = error.inner().to_string();
+
+    let message = location.as_ref()
+      .and_then(|location| message.strip_suffix(&format!(" at line {} column {}", location.line(), location.column())))
+      .unwrap_or(&message);
+    let message = message.split_once(": ")
+      .filter(|(prefix, _)| path.starts_with(prefix))
+      .map_or(message, |(_, message)| message);
+
+    Error::Schema(match (file, location) {
+      (Some(file), Some(location)) => format!("{}:{}:{}: `{}`: {}", file, location.line(), location.column(), path, message),
+      (Some(file), None) => format!("{}: `{}`: {}", file, path, message),
+      (None, _) => format!("`{}`: {}", path, message),
+    })
+  }
+}
+
+impl SourceSchema {
+  pub(super) fn into_source(self, code: String) -> Result<Source, Error> {
+    let fallback = match (self.fallback_file, self.fallback) {
+      (Some(path), _) => Some(JsonValue::from_str(&ConfigFile::new(&path, FileType::Fallback)?.read()?)
+        .map_err(|error| Error::String(format!("`sources.{}.fallback_file`: invalid JSON: {}", code, error)))?),
+      (None, fallback) => resolved(fallback).map(|fallback| fallback.0),
+    };
+
+    Ok(Source {
+      id: None,
+      url: self.url.ok_or_else(|| Error::String(format!("`sources.{}`: missing field `url`.", code)))?,
+      code,
+      method: resolved(self.method).map(|method| method.0).unwrap_or_default(),
+      params: multimap(self.params),
+      list_format: resolved(self.list_format).map(|format| format.0).unwrap_or_default(),
+      headers: multimap(self.headers),
+      forward: self.forward.map(Forward::from).unwrap_or_default(),
+      timeout: resolved(self.timeout).map(|timeout| timeout.0),
+      retry: self.retry.map(Retry::from),
+      circuit_breaker: self.circuit_breaker.map(CircuitBreaker::from),
+      cache: self.cache.map(Cache::from),
+      auth: self.auth.map(|auth| Auth::from(auth.0)).unwrap_or(Auth::None),
+      body: self.body.map(|body| Body::from(body.0)).unwrap_or(Body::None),
+      fallback,
+    })
+  }
+}
+
+impl DestinationSchema {
+  /// The destination with the codes of its sources and whether each is required.
+  pub(super) fn into_destination(self, code: String) -> Result<(Destination, Vec<(String, bool)>), Error> {
+    let filter = match (self.filter_file, self.filter) {
+      (Some(path), _) => Some(ConfigFile::new(&path, FileType::Filter)?.read()?),
+      (None, filter) => filter.map(|filter| filter.replace('\n', "").split_whitespace().collect::<Vec<_>>().join(" ")),
+    };
+
+    let sources = self.sources.unwrap_or_default().into_iter()
+      .map(|link| match link {
+        SourceLinkSchema::Code(source) => (source, true),
+        SourceLinkSchema::Link(link) => (link.source, resolved(link.required).unwrap_or(true)),
+      })
+      .collect();
+
+    Ok((Destination {
+      id: None,
+      path: self.path.ok_or_else(|| Error::String(format!("`destinations.{}`: missing field `path`.", code)))?,
+      code,
+      methods: self.methods
+        .map(|methods| methods.into_iter().filter_map(|method| method.0).map(|method| method.0).collect())
+        .unwrap_or_else(|| vec![Method::Get]),
+      is_active: resolved(self.is_active).unwrap_or_default(),
+      headers: multimap(self.headers),
+      is_auth: resolved(self.is_auth).unwrap_or_default(),
+      filter,
+      cache: self.cache.map(Cache::from),
+      deadline: resolved(self.deadline).map(|deadline| deadline.0),
+    }, sources))
+  }
+}
+
+impl AuthTokenSchema {
+  /// The token, stored by its hash, with the codes of the destinations it grants.
+  pub(super) fn into_token(self) -> (AuthToken, Vec<String>) {
+    let (value, expiration, destinations) = match self {
+      Self::Value(value) => (Hasher::hash_string(value), None, None),
+      Self::Entry(entry) => (
+        match (entry.value, entry.value_hash) {
+          (_, Some(hash)) => hash.0,
+          (value, None) => Hasher::hash_string(value.unwrap_or_default()),
+        },
+        resolved(entry.expiration).map(|expiration| expiration.0),
+        entry.destinations,
+      ),
+    };
+
+    (AuthToken { id: None, value, expiration }, destinations.unwrap_or_default())
+  }
+}
+
+/// The value of an optional field, absent as well for a placeholder that is only being checked.
+fn resolved<T>(value: Option<Interpolated<T>>) -> Option<T> {
+  value.and_then(|value| value.0)
+}
+
+fn multimap(values: Option<HashMap<String, MultiValueSchema>>) -> HashMap<String, MultiValue> {
+  values.unwrap_or_default().into_iter()
+    .map(|(key, value)| (key, match value {
+      MultiValueSchema::One(value) => MultiValue::One(value.0),
+      MultiValueSchema::Many(values) => MultiValue::Many(values.into_iter().map(|value| value.0).collect()),
+    }))
+    .collect()
+}
+
+impl From<ForwardSchema> for Forward {
+  fn from(value: ForwardSchema) -> Self {
+    Self {
+      query: value.query.unwrap_or_default().into_iter()
+        .map(|ForwardEntry(from, to)| (from, to))
+        .collect(),
+      headers: value.headers.unwrap_or_default().into_iter()
+        .map(|ForwardEntry(from, to)| (from.to_lowercase(), to))
+        .collect(),
+    }
+  }
+}
+
+impl From<RetrySchema> for Retry {
+  fn from(value: RetrySchema) -> Self {
+    Self {
+      max_attempts: resolved(value.max_attempts).unwrap_or(3),
+      base_backoff: resolved(value.base_backoff).map_or(Duration::from_secs(1), |backoff| backoff.0),
+      max_backoff: resolved(value.max_backoff).map_or(Duration::from_secs(30), |backoff| backoff.0),
+      jitter: resolved(value.jitter).unwrap_or(true),
+      statuses: value.statuses
+        .map(|statuses| statuses.into_iter().filter_map(|status| status.0).collect())
+        .unwrap_or_else(|| vec![429, 502, 503, 504]),
+      errors: value.errors
+        .map(|errors| errors.into_iter().filter_map(|error| error.0).map(|error| error.0).collect())
+        .unwrap_or_else(|| vec![RetryError::Timeout, RetryError::Connect]),
+      retry_after: resolved(value.retry_after).unwrap_or(true),
+    }
+  }
+}
+
+impl From<CircuitBreakerSchema> for CircuitBreaker {
+  fn from(value: CircuitBreakerSchema) -> Self {
+    Self {
+      failure_threshold: resolved(value.failure_threshold).map_or(5, |count| count.0),
+      open_duration: resolved(value.open_duration).map_or(Duration::from_secs(30), |duration| duration.0),
+      half_open_probes: resolved(value.half_open_probes).map_or(1, |count| count.0),
+    }
+  }
+}
+
+impl From<CacheSchema> for Cache {
+  fn from(value: CacheSchema) -> Self {
+    Self {
+      ttl: resolved(value.ttl).map_or(Duration::from_secs(60), |ttl| ttl.0),
+      max_entries: resolved(value.max_entries).unwrap_or(1000),
+      max_bytes: resolved(value.max_bytes),
+      cache_control: resolved(value.cache_control).unwrap_or_default(),
+      stale_while_revalidate: resolved(value.stale_while_revalidate).map(|duration| duration.0),
+      stale_if_error: resolved(value.stale_if_error).map(|duration| duration.0),
+    }
+  }
+}
+
+impl From<DestinationCacheSchema> for Cache {
+  fn from(value: DestinationCacheSchema) -> Self {
+    Self::from(CacheSchema {
+      ttl: value.ttl,
+      max_entries: value.max_entries,
+      max_bytes: value.max_bytes,
+      cache_control: None,
+      stale_while_revalidate: None,
+      stale_if_error: None,
+    })
+  }
+}
+
+impl From<AuthSchema> for Auth {
+  fn from(value: AuthSchema) -> Self {
+    match value {
+      AuthSchema::None {} => Self::None,
+      AuthSchema::Basic { username, password } => Self::Basic { username, password },
+      AuthSchema::Bearer { token } => Self::Bearer { token },
+      AuthSchema::Param { key, value } => Self::Param(key, value),
+    }
+  }
+}
+
+impl From<BodySchema> for Body {
+  fn from(value: BodySchema) -> Self {
+    let form = |form: Option<HashMap<String, ScalarSchema>>| form.unwrap_or_default().into_iter()
+      .map(|(key, value)| (key, value.0))
+      .collect();
+
+    match value {
+      BodySchema::None {} => Self::None,
+      BodySchema::Text { text } => Self::Text(text),
+      BodySchema::Json { json } => json.0.map_or(Self::None, |json| Self::Json(json.0)),
+      BodySchema::Form { form: fields } => Self::Form(form(fields)),
+      BodySchema::Multi { form: fields } => Self::Multi(form(fields)),
+      BodySchema::Passthrough {} => Self::Passthrough,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn source(content: &str) -> Result<Source, Error> {
+    ConfigSchema::validate("config.yaml", content)?;
+    let mut sources = ConfigSchema::read(&serde_yaml::from_str(content)?)?.sources.unwrap_or_default().0;
+    let (code, source) = sources.remove(0);
+    source.into_source(code)
+  }
+
+  #[test]
+  fn rejects_source_only_keys_in_destination_caches() {
+    let content = "destinations: {d: {path: /d, cache: {ttl: 5, stale_if_error: 30}}}";
+    let error = ConfigSchema::validate("config.yaml", content).unwrap_err().to_string();
+
+    assert!(error.contains("`destinations.d.cache.stale_if_error`: unknown field `stale_if_error`"), "{}", error);
+    assert!(ConfigSchema::validate("config.yaml", "destinations: {d: {path: /d, cache: {ttl: 5, max_entries: 10}}}").is_ok());
+  }
+
+  #[test]
+  fn reads_auth_and_body_without_type_as_none() {
+    let source = source("sources: {s: {url: /s, auth: {}, body: {}}}").unwrap();
+
+    assert!(matches!(source.auth, Auth::None));
+    assert!(matches!(source.body, Body::None));
+  }
+
+  #[test]
+  fn rejects_fields_of_auth_and_body_without_type() {
+    let error = source("sources: {s: {url: /s, auth: {token: t}}}").err().unwrap().to_string();
+    assert!(error.contains("`sources.s.auth`: unknown field `token`"), "{}", error);
+    assert!(error.contains("`type` is not set, so it is `none`"), "{}", error);
+
+    assert!(source("sources: {s: {url: /s, body: {text: t}}}").is_err());
+  }
+
+  #[test]
+  fn rejects_a_failure_threshold_below_one() {
+    let error = source("sources: {s: {url: /s, circuit_breaker: {failure_threshold: 0}}}").err().unwrap().to_string();
+    assert!(error.contains("`sources.s.circuit_breaker.failure_threshold`"), "{}", error);
+    assert!(error.contains("expected a number of at least 1"), "{}", error);
+
+    assert_eq!(source("sources: {s: {url: /s, circuit_breaker: {failure_threshold: 1}}}").unwrap()
+      .circuit_breaker.unwrap().failure_threshold, 1);
+  }
+
+  #[test]
+  fn rejects_half_open_probes_below_one() {
+    let error = source("sources: {s: {url: /s, circuit_breaker: {half_open_probes: \"0\"}}}").err().unwrap().to_string();
+    assert!(error.contains("`sources.s.circuit_breaker.half_open_probes`"), "{}", error);
+    assert!(error.contains("expected a number of at least 1"), "{}", error);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_yaml::Value as YamlValue;
+use sqlx::PgConnection;
+
+use crate::data::models::{AuthToken, Destination, Source};
+
+use super::{loader::Origins, schema::ConfigSchema, Error};
+
+/// Sources, destinations and tokens with their links, as declared by a config or stored in the database.
+///
+/// Destinations link to sources by code, each with whether the source is required.
+pub struct ConfigState {
+  pub sources: Vec<Source>,
+  pub destinations: Vec<(Destination, Vec<(String, bool)>)>,
+  pub auth_tokens: Vec<(AuthToken, Vec<String>)>,
+}
+
+impl TryFrom<&YamlValue> for ConfigState {
+  type Error = Error;
+
+  fn try_from(config: &YamlValue) -> Result<Self, Self::Error> {
+    Self::read(config, &Origins::default())
+  }
+}
+
+impl ConfigState {
+  /// Reads a merged config, locating validation errors in the files `origins` traces its entries to.
+  pub fn read(config: &YamlValue, origins: &Origins) -> Result<Self, Error> {
+    let config = ConfigSchema::read(config)?;
+
+    let state = Self {
+      sources: config.sources.unwrap_or_default().0.into_iter()
+        .map(|(code, source)| source.into_source(code))
+        .collect::<Result<_, _>>()?,
+      destinations: config.destinations.unwrap_or_default().0.into_iter()
+        .map(|(code, destination)| destination.into_destination(code))
+        .collect::<Result<_, _>>()?,
+      auth_tokens: config.auth_tokens.unwrap_or_default().into_iter()
+        .map(|token| token.into_token())
+        .collect(),
+    };
+
+    state.validate(origins)?;
+
+    Ok(state)
+  }
+
+  /// Checks that every referenced source and destination code is declared, no destination links a
+  /// source twice and every filter compiles, reporting each problem found.
+  fn validate(&self, origins: &Origins) -> Result<(), Error> {
+    let sources = self.sources.iter()
+      .map(|source| source.code.as_str())
+      .collect::<HashSet<_>>();
+    let destinations = self.destinations.iter()
+      .map(|(destination, _)| destination.code.as_str())
+      .collect::<HashSet<_>>();
+
+    let unknown_sources = self.destinations.iter()
+      .flat_map(|(destination, links)| links.iter().enumerate()
+        .filter(|(_, (code, _))| !sources.contains(code.as_str()))
+        .map(move |(index, (code, _))| origins.error(&format!("destinations.{}.sources[{}]", destination.code, index),
+          &format!("unknown source `{}`.", code))));
+    let duplicate_sources = self.destinations.iter()
+      .flat_map(|(destination, links)| links.iter().enumerate()
+        .filter(|(index, (code, _))| links[..*index].iter().any(|(previous, _)| previous == code))
+        .map(move |(index, (code, _))| origins.error(&format!("destinations.{}.sources[{}]", destination.code, index),
+          &format!("duplicate source `{}`.", code))));
+    let unknown_destinations = self.auth_tokens.iter().enumerate()
+      .flat_map(|(token_index, (_, codes))| codes.iter().enumerate()
+        .filter(|(_, code)| !destinations.contains(code.as_str()))
+        .map(move |(index, code)| origins.error(&format!("auth_tokens[{}].destinations[{}]", token_index, index),
+          &format!("unknown destination `{}`.", code))));
+
+    let invalid_filters = self.destinations.iter()
+      .filter_map(|(destination, _)| Some((destination, jq_rs::compile(destination.filter.as_ref()?).err()?)))
+      .map(|(destination, error)| origins.error(&format!("destinations.{}.filter", destination.code),
+        &format!("jq filter does not compile: {}.", error.to_string().trim())));
+
+    let errors = unknown_sources.chain(duplicate_sources).chain(unknown_destinations).chain(invalid_filters)
+      .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::Validation(errors))
+    }
+  }
+
+  pub async fn load(conn: &mut PgConnection) -> Result<Self, Error> {
+    let sources = Source::select_all(conn).await?;
+    let destinations = Destination::select_all(conn).await?;
+    let auth_tokens = AuthToken::select_all(conn).await?;
+    let source_links = Destination::select_source_links(conn).await?;
+    let token_links = AuthToken::select_destination_links(conn).await?;
+
+    let source_codes = sources.iter()
+      .filter_map(|source| source.id.map(|id| (id, source.code.clone())))
+      .collect::<HashMap<_, _>>();
+    let destination_codes = destinations.iter()
+      .filter_map(|destination| destination.id.map(|id| (id, destination.code.clone())))
+      .collect::<HashMap<_, _>>();
+
+    Ok(Self {
+      destinations: destinations.into_iter()
+        .map(|destination| {
+          let links = source_links.iter()
+            .filter(|(destination_id, _, _)| Some(*destination_id) == destination.id)
+            .filter_map(|(_, source_id, required)| Some((source_codes.get(source_id)?.clone(), *required)))
+            .collect();
+          (destination, links)
+        })
+        .collect(),
+      auth_tokens: auth_tokens.into_iter()
+        .map(|token| {
+          let codes = token_links.iter()
+            .filter(|(token_id, _)| Some(*token_id) == token.id)
+            .filter_map(|(_, destination_id)| destination_codes.get(destination_id).cloned())
+            .collect();
+          (token, codes)
+        })
+        .collect(),
+      sources,
+    })
+  }
+}
\ No newline at end of file
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use serde_yaml::{Mapping, Value as YamlValue};
+
+use super::{merge::deep_merge, Error};
+
+/// Expands `extends:` on sources, destinations and templates with the fields of the named
+/// `templates:`, applied in order and then overridden by the entry's own fields (see [`deep_merge`]).
+pub struct Templates<'a> {
+  templates: &'a Mapping,
+  resolved: HashMap<String, YamlValue>,
+}
+
+impl<'a> Templates<'a> {
+  /// Returns the config with every `extends:` expanded and the `templates:` section removed.
+  pub fn expand(mut config: YamlValue) -> Result<YamlValue, Error> {
+    let templates = match config.as_mapping_mut().and_then(|config| config.remove("templates")) {
+      Some(YamlValue::Mapping(templates)) => templates,
+      _ => Mapping::new(),
+    };
+
+    let mut expander = Templates { templates: &templates, resolved: HashMap::new() };
+    let mut errors = Vec::new();
+
+    for (section, required) in [("sources", "url"), ("destinations", "path")] {
+      let Some(YamlValue::Mapping(entries)) = config.get_mut(section) else {
+        continue;
+      };
+
+      for (code, entry) in entries.iter_mut() {
+        let path = format!("{}.{}", section, code.as_str().unwrap_or_default());
+
+        match expander.apply(entry.clone(), &path, &mut Vec::new()) {
+          Ok(expanded) => {
+            if expanded.get(required).is_none() {
+              errors.push(format!("`{}`: missing field `{}`.", path, required));
+            }
+            *entry = expanded;
+          },
+          Err(error) => errors.push(error),
+        }
+      }
+    }
+
+    if errors.is_empty() {
+      Ok(config)
+    } else {
+      Err(Error::Validation(errors))
+    }
+  }
+
+  fn apply(&mut self, mut entry: YamlValue, path: &str, stack: &mut Vec<String>) -> Result<YamlValue, String> {
+    let extends = match entry.as_mapping_mut().and_then(|entry| entry.remove("extends")) {
+      Some(YamlValue::String(name)) => vec![name],
+      Some(YamlValue::Sequence(names)) => names.into_iter()
+        .map(|name| name.as_str().map(str::to_owned)
+          .ok_or_else(|| format!("`{}.extends`: template names must be strings.", path)))
+        .collect::<Result<_, _>>()?,
+      Some(_) => Err(format!("`{}.extends`: expected a template name or a list of template names.", path))?,
+      None => return Ok(entry),
+    };
+
+    let mut base = YamlValue::Mapping(Mapping::new());
+    for name in extends {
+      base = deep_merge(base, self.template(&name, path, stack)?);
+    }
+
+    Ok(deep_merge(base, entry))
+  }
+
+  fn template(&mut self, name: &str, path: &str, stack: &mut Vec<String>) -> Result<YamlValue, String> {
+    if let Some(template) = self.resolved.get(name) {
+      return Ok(template.clone());
+    }
+
+    if stack.iter().any(|entry| entry == name) {
+      return Err(format!("`{}.extends`: template cycle {} -> {}.", path, stack.join(" -> "), name));
+    }
+
+    let template = self.templates.get(name)
+      .cloned()
+      .ok_or_else(|| format!("`{}.extends`: unknown template `{}`.", path, name))?;
+
+    stack.push(name.to_owned());
+    let template = self.apply(template, &format!("templates.{}", name), stack)?;
+    stack.pop();
+
+    self.resolved.insert(name.to_owned(), template.clone());
+    Ok(template)
+  }
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use sqlx::{postgres::PgRow, types::Json, Error as SqlxError, FromRow, PgConnection, Row};
+
+use crate::utils::Hasher;
+
+use super::{interpolation::Interpolation, loader::Origins, update_config, ConfigState, Error, Plan};
+
+/// An applied config, with the normalized snapshot it was built from, placeholders unresolved.
+#[derive(Serialize)]
+pub struct ConfigVersion {
+  pub id: String,
+  pub updated_on: String,
+  pub hash: String,
+  pub profile: Option<String>,
+  pub has_snapshot: bool,
+  pub rollback_of: Option<String>,
+  #[serde(skip)]
+  digest: Vec<u8>,
+  #[serde(skip)]
+  config: Option<JsonValue>,
+}
+
+impl ConfigVersion {
+  pub async fn select_all(conn: &mut PgConnection) -> Result<Vec<Self>, Error> {
+    Ok(sqlx::query_as("
+      SELECT config_versions.id::text AS id,
+            config_versions.updated_on,
+            config_versions.hash,
+            config_versions.config,
+            config_versions.profile,
+            config_versions.rollback_of::text AS rollback_of
+      FROM config_versions
+      ORDER BY config_versions.updated_on DESC;
+    ")
+    .fetch_all(conn)
+    .await?)
+  }
+
+  /// Finds a version by its id or a unique prefix of it.
+  pub async fn select_by_prefix(prefix: &str, conn: &mut PgConnection) -> Result<Self, Error> {
+    let mut versions: Vec<Self> = sqlx::query_as("
+      SELECT config_versions.id::text AS id,
+            config_versions.updated_on,
+            config_versions.hash,
+            config_versions.config,
+            config_versions.profile,
+            config_versions.rollback_of::text AS rollback_of
+      FROM config_versions
+      WHERE config_versions.id::text LIKE $1 || '%'
+      LIMIT 2;
+    ")
+    .bind(prefix)
+    .fetch_all(conn)
+    .await?;
+
+    match versions.len() {
+      0 => Err(SqlxError::RowNotFound)?,
+      1 => Ok(versions.remove(0)),
+      _ => Err(Error::String(format!("Config version `{}` is ambiguous.", prefix))),
+    }
+  }
+
+  fn state(&self) -> Result<ConfigState, Error> {
+    let config = self.config.as_ref()
+      .ok_or_else(|| Error::String(format!("Config version `{}` has no stored snapshot.", self.id)))?;
+
+    ConfigState::try_from(&Interpolation::resolve(serde_yaml::to_value(config)?)?)
+  }
+
+  /// What applying `to` over this version would change.
+  pub fn diff(&self, to: &Self) -> Result<Plan, Error> {
+    Plan::compare(&self.state()?, &to.state()?)
+  }
+
+  /// Reapplies this version's snapshot as a new version.
+  ///
+  /// The rollback stays in place until the config file itself changes.
+  pub async fn rollback(&self) -> Result<(), Error> {
+    let config = self.config.as_ref()
+      .ok_or_else(|| Error::String(format!("Config version `{}` has no stored snapshot.", self.id)))?;
+
+    println!("Rolling back configuration to version: ({})", &self.id);
+    update_config(serde_yaml::to_value(config)?, &Origins::default(), self.digest.clone(), self.profile.as_deref(), Some(&self.id)).await
+  }
+}
+
+impl FromRow<'_, PgRow> for ConfigVersion {
+  fn from_row(row: &'_ PgRow) -> Result<Self, sqlx::Error> {
+    let digest = row.try_get::<Vec<u8>, _>("hash")?;
+    let config = row.try_get::<Option<Json<JsonValue>>, _>("config")?.map(|json| json.0);
+
+    Ok(Self {
+      id: row.try_get("id")?,
+      updated_on: row.try_get::<DateTime<Utc>, _>("updated_on")?.to_rfc3339(),
+      hash: Hasher::to_hex(&digest),
+      profile: row.try_get("profile")?,
+      has_snapshot: config.is_some(),
+      rollback_of: row.try_get("rollback_of")?,
+      digest,
+      config,
+    })
+  }
+}
\ No newline at end of file
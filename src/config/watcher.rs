@@ -0,0 +1,60 @@
+use std::{collections::HashMap, fs, future::Future, time::{Duration, SystemTime}};
+
+use tokio::{signal::unix::{signal, SignalKind}, select, time::{interval, MissedTickBehavior}};
+
+use super::{config_path, watched_files, Error};
+
+/// Runs `reload` whenever a watched config file changes on disk or the process receives SIGHUP.
+///
+/// Files are polled every `CONFIG_WATCH_INTERVAL` seconds (default 5, `0` disables polling).
+/// A failed reload is logged and the previous configuration stays live.
+pub async fn watch<F, Fut>(reload: F) -> Result<(), Error>
+where
+  F: Fn() -> Fut,
+  Fut: Future<Output = Result<(), Error>>,
+{
+  let poll = dotenv::var("CONFIG_WATCH_INTERVAL").ok()
+    .map(|val| val.parse::<u64>()
+      .map_err(|_| Error::String(format!("CONFIG_WATCH_INTERVAL `{}` invalid.", val))))
+    .transpose()?
+    .unwrap_or(5);
+
+  let mut hangup = signal(SignalKind::hangup())?;
+  let mut ticker = interval(Duration::from_secs(poll.max(1)));
+  ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+  let mut stamps = modified_times();
+
+  loop {
+    select! {
+      _ = ticker.tick(), if poll > 0 => {
+        let current = modified_times();
+        if current == stamps {
+          continue;
+        }
+        stamps = current;
+        println!("Configuration files changed, reloading.");
+      },
+      _ = hangup.recv() => {
+        stamps = modified_times();
+        println!("Received SIGHUP, reloading configuration.");
+      },
+    }
+
+    // Stamps are taken before reloading, so an edit made while it runs triggers another reload.
+    if let Err(error) = reload().await {
+      println!("Configuration reload failed, keeping previous configuration: {}", error);
+    }
+  }
+}
+
+fn modified_times() -> HashMap<String, Option<SystemTime>> {
+  watched_files()
+    .unwrap_or_else(|_| vec![config_path()])
+    .into_iter()
+    .map(|path| {
+      let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+      (path, modified)
+    })
+    .collect()
+}
\ No newline at end of file
@@ -1,7 +1,4 @@
-use std::{
-  collections::HashMap, str::FromStr, time::Duration
-};
-use chrono::{DateTime, Utc};
+use std::time::Duration;
 use serde_yaml::Value as YamlValue;
 
 use super::Error;
@@ -9,25 +6,14 @@ use super::Error;
 pub struct YamlParser;
 
 impl YamlParser {
-  pub fn get_req(data: &YamlValue, value: &str) -> Result<YamlValue, Error> {
-    data.get(value).map(|val| val.clone())
-      .ok_or(Error::String(format!("Required field `{}` is missing.", value)))
-  }
-
-  pub fn to_hashmap_option(mapping: Option<&YamlValue>) -> Result<Option<HashMap<String, String>>, Error> {
-    mapping.map(|val|
-      val.as_mapping()
-      .ok_or(Error::Str("`Value` could not be converted to `Mapping`."))?
-      .iter()
-      .map(|(key, value)|
-        Ok((Self::to_string(key)?, serde_yaml::to_string(value)?.replace('\n', ""))))
-      .collect()
-    )
-    .transpose()
-  }
-
-  pub fn to_str(value: &YamlValue) -> Result<&str, Error> {
-    value.as_str().ok_or(Error::Str("`Value` could not be converted to `str`."))
+  /// Renders a string, number or boolean the way it is written.
+  pub fn to_scalar_string(value: &YamlValue) -> Result<String, Error> {
+    match value {
+      YamlValue::String(string) => Ok(string.to_owned()),
+      YamlValue::Number(number) => Ok(number.to_string()),
+      YamlValue::Bool(bool) => Ok(bool.to_string()),
+      _ => Err(Error::Str("`Value` could not be converted to a string, number or boolean.")),
+    }
   }
 
   pub fn to_string(value: &YamlValue) -> Result<String, Error> {
@@ -35,40 +21,77 @@ impl YamlParser {
       .ok_or(Error::Str("`Value` could not be converted to `String`."))
   }
 
-  pub fn to_u64(value: &YamlValue) -> Result<u64, Error> {
-    value.as_u64().ok_or(Error::Str("`Value` could not be converted to `u64`."))
+  /// Reads a number of seconds, or a number with a unit: `250ms`, `1.5s`, `2m`, `1h`.
+  pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|char: char| !char.is_ascii_digit() && char != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+
+    let seconds = match unit.trim() {
+      "ms" => number / 1000.0,
+      "" | "s" => number,
+      "m" => number * 60.0,
+      "h" => number * 3600.0,
+      _ => return None,
+    };
+
+    Self::from_secs(seconds)
   }
 
-  pub fn to_duration(value: Option<&YamlValue>) -> Result<Option<Duration>, Error> {
-    value.map(|val| Ok(Duration::from_secs(Self::to_u64(val)?))).transpose()
+  /// Rounds to whole microseconds, the precision of the `INTERVAL` column durations are stored in.
+  pub fn from_secs(seconds: f64) -> Option<Duration> {
+    let micros = (seconds * 1_000_000.0).round();
+    (micros.is_finite() && micros >= 0.0 && micros <= u64::MAX as f64)
+      .then(|| Duration::from_micros(micros as u64))
   }
 
   pub fn to_string_option(value: Option<&YamlValue>) -> Result<Option<String>, Error> {
-    value.map(|val| Self::to_string(val)).transpose()
+    value.map(Self::to_string).transpose()
   }
 
-  pub fn to_str_option(value: Option<&YamlValue>) -> Result<Option<&str>, Error> {
-    value.map(|val| Self::to_str(val)).transpose()
+  pub fn to_sequence_option(value: Option<&YamlValue>) -> Result<Option<&Vec<YamlValue>>, Error> {
+    value.map(|val| val.as_sequence().ok_or(Error::Str("`Value` could not be converted to `Sequence`."))).transpose()
   }
 
-  pub fn to_bool_option(value: Option<&YamlValue>) -> Result<Option<bool>, Error> {
-    value.map(|val| Ok(val.as_bool().ok_or(Error::Str("`Value` could not be converted to `bool`."))?)).transpose()
+  pub fn vec_to_string(value: &[YamlValue]) -> Result<Vec<String>, Error> {
+    value.iter().map(Self::to_string).collect()
   }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  pub fn to_datetime_option(value: Option<&YamlValue>) -> Result<Option<DateTime<Utc>>, Error> {
-    value.map(|val| Ok(DateTime::from_str(Self::to_str(val)?)?)).transpose()
+  #[test]
+  fn reads_plain_numbers_as_seconds() {
+    assert_eq!(YamlParser::parse_duration("5"), Some(Duration::from_secs(5)));
+    assert_eq!(YamlParser::parse_duration("1.5"), Some(Duration::from_millis(1500)));
+    assert_eq!(YamlParser::parse_duration(" 2 "), Some(Duration::from_secs(2)));
   }
 
-  pub fn to_string_req(data: &YamlValue, value: &str) -> Result<String, Error> {
-    Self::to_string(&Self::get_req(data, value)?)
+  #[test]
+  fn reads_units() {
+    assert_eq!(YamlParser::parse_duration("250ms"), Some(Duration::from_millis(250)));
+    assert_eq!(YamlParser::parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+    assert_eq!(YamlParser::parse_duration("2m"), Some(Duration::from_secs(120)));
+    assert_eq!(YamlParser::parse_duration("1h"), Some(Duration::from_secs(3600)));
+    assert_eq!(YamlParser::parse_duration("10 s"), Some(Duration::from_secs(10)));
   }
 
-  pub fn to_string_option_multiline(value: Option<&YamlValue>) -> Result<Option<String>, Error> {
-    Ok(Self::to_str_option(value)?.map(|val| val.replace('\n', "").trim()
-      .split_whitespace().collect::<Vec<_>>().join(" ")))
+  #[test]
+  fn rounds_to_microseconds() {
+    assert_eq!(YamlParser::parse_duration("0.0000004s"), Some(Duration::ZERO));
+    assert_eq!(YamlParser::parse_duration("0.0015ms"), Some(Duration::from_micros(2)));
   }
 
-  pub fn vec_to_string(value: &Vec<YamlValue>) -> Result<Vec<String>, Error> {
-    value.iter().map(|val| Self::to_string(val)).collect()
+  #[test]
+  fn rejects_invalid_durations() {
+    assert_eq!(YamlParser::parse_duration(""), None);
+    assert_eq!(YamlParser::parse_duration("s"), None);
+    assert_eq!(YamlParser::parse_duration("-5s"), None);
+    assert_eq!(YamlParser::parse_duration("5d"), None);
+    assert_eq!(YamlParser::parse_duration("1.2.3s"), None);
+    assert_eq!(YamlParser::parse_duration("5 ms s"), None);
   }
 }
\ No newline at end of file
@@ -26,8 +26,8 @@ impl From<SqlxError> for Error {
   }
 }
 
-impl Into<StdError> for Error {
-  fn into(self) -> StdError {
-    StdError::other(self.to_string())
+impl From<Error> for StdError {
+  fn from(value: Error) -> Self {
+    StdError::other(value.to_string())
   }
 }
\ No newline at end of file
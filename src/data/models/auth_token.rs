@@ -46,12 +46,54 @@ impl AuthToken {
     .await?)
   }
 
+  pub async fn select_all(conn: &mut PgConnection) -> Result<Vec<Self>, Error> {
+    Ok(sqlx::query_as("
+      SELECT auth_tokens.*
+      FROM auth_tokens;
+    ")
+    .fetch_all(conn)
+    .await?)
+  }
+
+  /// Every `(auth_token_id, destination_id)` link.
+  pub async fn select_destination_links(conn: &mut PgConnection) -> Result<Vec<(i32, i32)>, Error> {
+    Ok(sqlx::query_as("
+      SELECT destinations__auth_tokens.auth_token_id,
+            destinations__auth_tokens.destination_id
+      FROM destinations__auth_tokens;
+    ")
+    .fetch_all(conn)
+    .await?)
+  }
+
+  /// Deletes every token whose hashed value is not in `values`, with its links, returning the deleted ids.
+  pub async fn delete_except(values: &[Vec<u8>], conn: &mut PgConnection) -> Result<Vec<i32>, Error> {
+    sqlx::query("
+      DELETE FROM destinations__auth_tokens
+      USING auth_tokens
+      WHERE destinations__auth_tokens.auth_token_id = auth_tokens.id
+        AND auth_tokens.value <> ALL($1);
+    ")
+    .bind(values)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(sqlx::query_scalar("
+      DELETE FROM auth_tokens
+      WHERE auth_tokens.value <> ALL($1)
+      RETURNING auth_tokens.id;
+    ")
+    .bind(values)
+    .fetch_all(conn)
+    .await?)
+  }
+
   pub async fn unlink_destinations(&self, conn: &mut PgConnection) -> Result<(), Error> {
     sqlx::query("
       DELETE FROM destinations__auth_tokens
       WHERE destinations__auth_tokens.auth_token_id = $1;
     ")
-    .bind(&self.id)
+    .bind(self.id)
     .execute(conn)
     .await?;
     
@@ -65,7 +107,7 @@ impl AuthToken {
       FROM destinations
       WHERE destinations.code = ANY($2);
     ")
-    .bind(&self.id)
+    .bind(self.id)
     .bind(destination_codes)
     .execute(conn)
     .await?;
@@ -93,7 +135,7 @@ impl Queryable for AuthToken {
       RETURNING auth_tokens.*;
     ")
     .bind(&self.value)
-    .bind(&self.expiration)
+    .bind(self.expiration)
     .fetch_one(conn)
     .await?)
   }
@@ -107,8 +149,8 @@ impl Queryable for AuthToken {
       RETURNING auth_tokens.*;
     ")
     .bind(&self.value)
-    .bind(&self.expiration)
-    .bind(&self.id)
+    .bind(self.expiration)
+    .bind(self.id)
     .fetch_one(conn)
     .await?)
   }
@@ -118,7 +160,7 @@ impl Queryable for AuthToken {
       DELETE FROM auth_tokens
       WHERE auth_tokens.id = $1;
     ")
-    .bind(&self.id)
+    .bind(self.id)
     .execute(conn)
     .await?;
 
@@ -139,7 +181,7 @@ impl Queryable for AuthToken {
 
   async fn insert_or_update(&self, conn: &mut PgConnection) -> Result<Self, Error> {
     Ok(match Self::select_by_value(self.value.clone(), conn).await? {
-      Some(token) => token.update(conn).await?,
+      Some(token) => Self { id: token.id, value: self.value.clone(), expiration: self.expiration }.update(conn).await?,
       None => self.insert(conn).await?,
     })
   }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 use serde::{Serialize, Deserialize};
 use sqlx::{
   PgConnection, postgres::PgRow,
@@ -7,65 +7,82 @@ use sqlx::{
 };
 
 use crate::data::queryable::QueryableCode;
+use crate::data::types::{Cache, Method, MultiValue};
 use crate::data::Error;
 use crate::data::Queryable;
 
-use super::{AuthToken, Source};
+use super::interval;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Destination {
   pub id: Option<i32>,
   pub code: String,
   pub path: String,
+  pub methods: Vec<Method>,
   pub is_active: bool,
-  pub headers: HashMap<String, String>,
+  pub headers: HashMap<String, MultiValue>,
   pub filter: Option<String>,
   pub is_auth: bool,
+  /// Caches the filtered output; only `ttl` and the size limits apply here.
+  pub cache: Option<Cache>,
+  /// Bounds the time spent on all sources together.
+  pub deadline: Option<Duration>,
 }
 
 impl Destination {
-  pub async fn select_by_path(path: String, conn: &mut PgConnection) -> Result<Self, Error> {
+  pub async fn select_all(conn: &mut PgConnection) -> Result<Vec<Self>, Error> {
     Ok(sqlx::query_as("
       SELECT destinations.*
       FROM destinations
-      WHERE destinations.path = $1
-      LIMIT 1;
+      ORDER BY destinations.is_active DESC, destinations.id ASC;
     ")
-    .bind(path)
-    .fetch_one(conn)
+    .fetch_all(conn)
     .await?)
   }
 
-  pub async fn get_sources(&self, conn: &mut PgConnection) -> Result<Vec<Source>, Error> {
+  /// Every destination to source link, with whether the source is required.
+  pub async fn select_source_links(conn: &mut PgConnection) -> Result<Vec<(i32, i32, bool)>, Error> {
     Ok(sqlx::query_as("
-      SELECT sources.*
-      FROM destinations
-      INNER JOIN destinations__sources
-        ON destinations__sources.destination_id = destinations.id
-      INNER JOIN sources
-        ON sources.id = destinations__sources.source_id
-      WHERE destinations.path = $1
-      ORDER BY sources.id ASC;
+      SELECT destinations__sources.destination_id,
+            destinations__sources.source_id,
+            destinations__sources.required
+      FROM destinations__sources
+      ORDER BY destinations__sources.source_id ASC;
     ")
-    .bind(&self.path)
     .fetch_all(conn)
     .await?)
   }
 
-  pub async fn is_token_for(&self, auth_token: &AuthToken, conn: &mut PgConnection) -> Result<bool, Error> {
-    match sqlx::query("
-      SELECT destinations__auth_tokens.id
-      FROM destinations__auth_tokens
-      WHERE destinations__auth_tokens.destination_id = $1
-        AND destinations__auth_tokens.auth_token_id = $2;
+  /// Deletes every destination whose code is not in `codes`, with its links, returning the deleted codes.
+  pub async fn delete_except(codes: &[String], conn: &mut PgConnection) -> Result<Vec<String>, Error> {
+    sqlx::query("
+      DELETE FROM destinations__sources
+      USING destinations
+      WHERE destinations__sources.destination_id = destinations.id
+        AND destinations.code <> ALL($1);
     ")
-    .bind(&self.id)
-    .bind(&auth_token.id)
-    .fetch_optional(conn)
-    .await? {
-      Some(_) => Ok(auth_token.is_valid()),
-      None => Ok(false),
-    }
+    .bind(codes)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("
+      DELETE FROM destinations__auth_tokens
+      USING destinations
+      WHERE destinations__auth_tokens.destination_id = destinations.id
+        AND destinations.code <> ALL($1);
+    ")
+    .bind(codes)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(sqlx::query_scalar("
+      DELETE FROM destinations
+      WHERE destinations.code <> ALL($1)
+      RETURNING destinations.code;
+    ")
+    .bind(codes)
+    .fetch_all(conn)
+    .await?)
   }
 
   pub async fn unlink_sources(&self, conn: &mut PgConnection) -> Result<(), Error> {
@@ -73,22 +90,27 @@ impl Destination {
       DELETE FROM destinations__sources
       WHERE destinations__sources.destination_id = $1;
     ")
-    .bind(&self.id)
+    .bind(self.id)
     .execute(conn)
     .await?;
     
     Ok(())
   }
 
-  pub async fn link_sources(&self, source_codes: Vec<String>, conn: &mut PgConnection) -> Result<(), Error> {
+  /// Links the sources given as code and required flag pairs.
+  pub async fn link_sources(&self, source_links: Vec<(String, bool)>, conn: &mut PgConnection) -> Result<(), Error> {
+    let (source_codes, required): (Vec<_>, Vec<_>) = source_links.into_iter().unzip();
+
     sqlx::query("
-      INSERT INTO destinations__sources (destination_id, source_id)
-      SELECT $1 AS destination_id, sources.id AS source_id
-      FROM sources
-      WHERE sources.code = ANY($2);
+      INSERT INTO destinations__sources (destination_id, source_id, required)
+      SELECT $1 AS destination_id, sources.id AS source_id, links.required
+      FROM UNNEST($2::VARCHAR[], $3::BOOLEAN[]) AS links(code, required)
+      INNER JOIN sources
+        ON sources.code = links.code;
     ")
-    .bind(&self.id)
+    .bind(self.id)
     .bind(source_codes)
+    .bind(required)
     .execute(conn)
     .await?;
 
@@ -110,16 +132,19 @@ impl Queryable for Destination {
 
   async fn insert(&self, conn: &mut PgConnection) -> Result<Self, Error> {
     Ok(sqlx::query_as("
-      INSERT INTO destinations (code, path, is_active, headers, filter, is_auth)
-      VALUES ($1, $2, $3, $4, $5, $6)
+      INSERT INTO destinations (code, path, methods, is_active, headers, filter, is_auth, cache, deadline)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
       RETURNING destinations.*;
     ")
     .bind(&self.code)
     .bind(&self.path)
-    .bind(&self.is_active)
+    .bind(&self.methods)
+    .bind(self.is_active)
     .bind(Json(&self.headers))
     .bind(&self.filter)
-    .bind(&self.is_auth)
+    .bind(self.is_auth)
+    .bind(self.cache.as_ref().map(Json))
+    .bind(self.deadline)
     .fetch_one(conn)
     .await?)
   }
@@ -128,18 +153,24 @@ impl Queryable for Destination {
     Ok(sqlx::query_as("
       UPDATE destinations
       SET path = $1,
-          is_active = $2,
-          headers = $3,
-          filter = $4,
-          is_auth = $5
-      WHERE destinations.code = $6
+          methods = $2,
+          is_active = $3,
+          headers = $4,
+          filter = $5,
+          is_auth = $6,
+          cache = $7,
+          deadline = $8
+      WHERE destinations.code = $9
       RETURNING destinations.*;
     ")
     .bind(&self.path)
-    .bind(&self.is_active)
+    .bind(&self.methods)
+    .bind(self.is_active)
     .bind(Json(&self.headers))
     .bind(&self.filter)
-    .bind(&self.is_auth)
+    .bind(self.is_auth)
+    .bind(self.cache.as_ref().map(Json))
+    .bind(self.deadline)
     .bind(&self.code)
     .fetch_one(conn)
     .await?)
@@ -189,10 +220,13 @@ impl FromRow<'_, PgRow> for Destination {
       id: row.try_get("id")?,
       code: row.try_get("code")?,
       path: row.try_get("path")?,
+      methods: row.try_get_unchecked("methods")?,
       is_active: row.try_get("is_active")?,
-      headers: row.try_get::<Json<HashMap<String, String>>, _>("headers")?.0,
+      headers: row.try_get::<Json<HashMap<String, MultiValue>>, _>("headers")?.0,
       filter: row.try_get("filter")?,
       is_auth: row.try_get("is_auth")?,
+      cache: row.try_get::<Option<Json<Cache>>, _>("cache")?.map(|json| json.0),
+      deadline: interval(row, "deadline")?,
     })
   }
 }
\ No newline at end of file
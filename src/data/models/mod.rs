@@ -1,7 +1,27 @@
+use std::time::Duration;
+
+use sqlx::{postgres::{types::PgInterval, PgRow}, Row};
+
 mod destination;
 mod source;
 mod auth_token;
 
 pub use self::destination::Destination;
 pub use self::source::Source;
-pub use self::auth_token::AuthToken;
\ No newline at end of file
+pub use self::auth_token::AuthToken;
+
+/// Reads an interval column, rejecting intervals in months, which have no fixed length, and
+/// negative ones.
+fn interval(row: &PgRow, column: &str) -> Result<Option<Duration>, sqlx::Error> {
+  row.try_get::<Option<PgInterval>, _>(column)?
+    .map(|interval| match interval.months {
+      0 => i64::from(interval.days).checked_mul(86_400_000_000)
+        .and_then(|days| days.checked_add(interval.microseconds))
+        .and_then(|micros| u64::try_from(micros).ok())
+        .map(Duration::from_micros)
+        .ok_or("interval is negative or out of range"),
+      _ => Err("interval in months has no fixed length"),
+    }
+    .map_err(|message| sqlx::Error::ColumnDecode { index: column.to_owned(), source: message.into() }))
+    .transpose()
+}
\ No newline at end of file
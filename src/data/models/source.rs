@@ -2,24 +2,66 @@ use std::{collections::HashMap, time::Duration};
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 use sqlx::{
-  postgres::{types::PgInterval, PgRow}, prelude::FromRow, types::Json, PgConnection, Row
+  postgres::PgRow, prelude::FromRow, types::Json, PgConnection, Row
 };
 
-use crate::data::{queryable::QueryableCode, types::{Auth, Body}, Error, Queryable};
+use crate::data::{queryable::QueryableCode, types::{Auth, Body, Cache, CircuitBreaker, Forward, ListFormat, Method, MultiValue, Retry}, Error, Queryable};
+
+use super::interval;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Source {
   pub id: Option<i32>,
   pub code: String,
+  pub method: Method,
   pub url: String,
-  pub params: HashMap<String, String>,
-  pub headers: HashMap<String, String>,
+  pub params: HashMap<String, MultiValue>,
+  pub list_format: ListFormat,
+  pub headers: HashMap<String, MultiValue>,
+  pub forward: Forward,
   pub auth: Auth,
   pub timeout: Option<Duration>,
+  pub retry: Option<Retry>,
+  pub circuit_breaker: Option<CircuitBreaker>,
+  pub cache: Option<Cache>,
   pub body: Body,
   pub fallback: Option<Value>,
 }
 
+impl Source {
+  pub async fn select_all(conn: &mut PgConnection) -> Result<Vec<Self>, Error> {
+    Ok(sqlx::query_as("
+      SELECT sources.*
+      FROM sources
+      ORDER BY sources.id ASC;
+    ")
+    .fetch_all(conn)
+    .await?)
+  }
+
+  /// Deletes every source whose code is not in `codes`, with its links, returning the deleted codes.
+  pub async fn delete_except(codes: &[String], conn: &mut PgConnection) -> Result<Vec<String>, Error> {
+    sqlx::query("
+      DELETE FROM destinations__sources
+      USING sources
+      WHERE destinations__sources.source_id = sources.id
+        AND sources.code <> ALL($1);
+    ")
+    .bind(codes)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(sqlx::query_scalar("
+      DELETE FROM sources
+      WHERE sources.code <> ALL($1)
+      RETURNING sources.code;
+    ")
+    .bind(codes)
+    .fetch_all(conn)
+    .await?)
+  }
+}
+
 impl Queryable for Source {
   async fn select_by_id(id: i32, conn: &mut PgConnection) -> Result<Self, Error> {
     Ok(sqlx::query_as::<_, Self>("
@@ -36,41 +78,53 @@ impl Queryable for Source {
     Ok(sqlx::query_as("
       INSERT INTO sources (
         code,
+        method,
         url,
         params,
         headers,
+        forward,
         auth_type,
         auth_username,
         auth_password,
         auth_token,
         auth_param,
         timeout,
+        retry,
+        circuit_breaker,
+        cache,
         body_type,
         body_text,
         body_json,
         body_form,
         body_multi,
-        fallback
+        fallback,
+        list_format
       )
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
       RETURNING sources.*;
     ")
     .bind(&self.code)
+    .bind(self.method)
     .bind(&self.url)
     .bind(Json(&self.params))
     .bind(Json(&self.headers))
+    .bind(Json(&self.forward))
     .bind(&self.auth)
     .bind(self.auth.username())
     .bind(self.auth.password())
     .bind(self.auth.token())
-    .bind(self.auth.param().map(|param| Json(param)))
-    .bind(&self.timeout)
+    .bind(self.auth.param().map(|(key, value)| Json(HashMap::from([(key, value)]))))
+    .bind(self.timeout)
+    .bind(self.retry.as_ref().map(Json))
+    .bind(self.circuit_breaker.as_ref().map(Json))
+    .bind(self.cache.as_ref().map(Json))
     .bind(&self.body)
-    .bind(&self.body.text())
-    .bind(&self.body.json())
-    .bind(&self.body.form().map(|form| Json(form)))
-    .bind(&self.body.multi().map(|multi| Json(multi)))
+    .bind(self.body.text())
+    .bind(self.body.json())
+    .bind(self.body.form().map(Json))
+    .bind(self.body.multi().map(Json))
     .bind(&self.fallback)
+    .bind(self.list_format)
     .fetch_one(conn)
     .await?)
   }
@@ -78,39 +132,51 @@ impl Queryable for Source {
   async fn update(&self, conn: &mut PgConnection) -> Result<Self, Error> {
     Ok(sqlx::query_as("
       UPDATE sources
-      SET url = $1,
-          params = $2,
-          headers = $3,
-          auth_type = $4,
-          auth_username = $5,
-          auth_password = $6,
-          auth_token = $7,
-          auth_param = $8,
-          timeout = $9,
-          body_type = $10,
-          body_text = $11,
-          body_json = $12,
-          body_form = $13,
-          body_multi = $14,
-          fallback = $15
-      WHERE sources.code = $16
+      SET method = $1,
+          url = $2,
+          params = $3,
+          headers = $4,
+          forward = $5,
+          auth_type = $6,
+          auth_username = $7,
+          auth_password = $8,
+          auth_token = $9,
+          auth_param = $10,
+          timeout = $11,
+          retry = $12,
+          circuit_breaker = $13,
+          cache = $14,
+          body_type = $15,
+          body_text = $16,
+          body_json = $17,
+          body_form = $18,
+          body_multi = $19,
+          fallback = $20,
+          list_format = $21
+      WHERE sources.code = $22
       RETURNING sources.*;
     ")
+    .bind(self.method)
     .bind(&self.url)
     .bind(Json(&self.params))
     .bind(Json(&self.headers))
+    .bind(Json(&self.forward))
     .bind(&self.auth)
     .bind(self.auth.username())
     .bind(self.auth.password())
     .bind(self.auth.token())
-    .bind(self.auth.param().map(|param| Json(param)))
-    .bind(&self.timeout)
+    .bind(self.auth.param().map(|(key, value)| Json(HashMap::from([(key, value)]))))
+    .bind(self.timeout)
+    .bind(self.retry.as_ref().map(Json))
+    .bind(self.circuit_breaker.as_ref().map(Json))
+    .bind(self.cache.as_ref().map(Json))
     .bind(&self.body)
-    .bind(&self.body.text())
-    .bind(&self.body.json())
-    .bind(&self.body.form().map(|form| Json(form)))
-    .bind(&self.body.multi().map(|multi| Json(multi)))
+    .bind(self.body.text())
+    .bind(self.body.json())
+    .bind(self.body.form().map(Json))
+    .bind(self.body.multi().map(Json))
     .bind(&self.fallback)
+    .bind(self.list_format)
     .bind(&self.code)
     .fetch_one(conn)
     .await?)
@@ -121,7 +187,7 @@ impl Queryable for Source {
       DELETE FROM sources
       WHERE sources.id = $1;
     ")
-    .bind(&self.id)
+    .bind(self.id)
     .execute(conn)
     .await?;
 
@@ -159,12 +225,17 @@ impl FromRow<'_, PgRow> for Source {
     Ok(Self {
       id: row.try_get("id")?,
       code: row.try_get("code")?,
+      method: Method::from_row(row)?,
       url: row.try_get("url")?,
-      params: row.try_get::<Json<HashMap<String, String>>, _>("params")?.0,
-      headers: row.try_get::<Json<HashMap<String, String>>, _>("headers")?.0,
+      params: row.try_get::<Json<HashMap<String, MultiValue>>, _>("params")?.0,
+      list_format: ListFormat::from_row(row)?,
+      headers: row.try_get::<Json<HashMap<String, MultiValue>>, _>("headers")?.0,
+      forward: row.try_get::<Json<Forward>, _>("forward")?.0,
       auth: Auth::from_row(row)?,
-      timeout: row.try_get::<Option<PgInterval>, _>("timeout")?
-        .map(|interval| Duration::from_micros(interval.microseconds as u64)),
+      timeout: interval(row, "timeout")?,
+      retry: row.try_get::<Option<Json<Retry>>, _>("retry")?.map(|json| json.0),
+      circuit_breaker: row.try_get::<Option<Json<CircuitBreaker>>, _>("circuit_breaker")?.map(|json| json.0),
+      cache: row.try_get::<Option<Json<Cache>>, _>("cache")?.map(|json| json.0),
       body: Body::from_row(row)?,
       fallback: row.try_get("fallback")?,
     })
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::{self, Display, Formatter}};
 use serde::{Serialize, Deserialize};
 use sqlx::{
   encode::IsNull,
@@ -7,9 +7,6 @@ use sqlx::{
   FromRow, Postgres,
   Row, Type
 };
-use serde_yaml::Value as YamlValue;
-
-use crate::config::{Error, YamlParser};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Auth {
@@ -49,9 +46,9 @@ impl Auth {
   }
 }
 
-impl ToString for Auth {
-  fn to_string(&self) -> String {
-    String::from(match self {
+impl Display for Auth {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
       Self::None => "none",
       Self::Basic { username: _, password: _ } => "basic",
       Self::Bearer { token: _ } => "bearer",
@@ -85,28 +82,7 @@ impl FromRow<'_, PgRow> for Auth {
 
         Self::Param(vals.0.to_owned(), vals.1.to_owned())
       },
-      "none" | _ => Self::None,
-    })
-  }
-}
-
-impl TryFrom<Option<&YamlValue>> for Auth {
-  type Error = Error;
-
-  fn try_from(value: Option<&YamlValue>) -> Result<Self, Self::Error> {
-    Ok(if let Some(auth) = value {
-      match YamlParser::to_str_option(auth.get("type"))? {
-        Some("basic") => Self::Basic {
-          username: YamlParser::to_string_req(auth, "username")?,
-          password: YamlParser::to_string_req(auth, "password")?
-        },
-        Some("bearer") => Self::Bearer { token: YamlParser::to_string_req(auth, "token")? },
-        Some("param") => Self::Param(YamlParser::to_string_req(auth, "key")?, YamlParser::to_string_req(auth, "value")?),
-        Some("none") | None => Self::None,
-        Some(auth_type) => Err(Error::String(format!("Souce auth type `{}` invalid.", auth_type)))?,
-      }
-    } else {
-      Self::None
+      _ => Self::None,
     })
   }
 }
\ No newline at end of file
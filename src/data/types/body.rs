@@ -1,13 +1,9 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, fmt::{self, Display, Formatter}};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::{
   encode::IsNull, error::BoxDynError, postgres::{PgRow, PgTypeInfo}, types::Json, Database, Encode, FromRow, Postgres, Row, Type
 };
-use serde_json::Value as JsonValue;
-use serde_yaml::Value as YamlValue;
-
-use crate::config::{Error, YamlParser};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Body {
@@ -16,6 +12,7 @@ pub enum Body {
   Json(Value),
   Form(HashMap<String, String>),
   Multi(HashMap<String, String>),
+  Passthrough,
 }
 
 impl Body {
@@ -48,14 +45,15 @@ impl Body {
   }
 }
 
-impl ToString for Body {
-  fn to_string(&self) -> String {
-    String::from(match self {
+impl Display for Body {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
       Self::None => "none",
       Self::Text(_) => "text",
       Self::Json(_) => "json",
       Self::Form(_) => "form",
       Self::Multi(_) => "multi",
+      Self::Passthrough => "passthrough",
     })
   }
 }
@@ -81,26 +79,8 @@ impl FromRow<'_, PgRow> for Body {
       "json" => Self::Json(row.try_get("body_json")?),
       "form" => Self::Form(row.try_get::<Json<_>, _>("body_form")?.0),
       "multi" => Self::Multi(row.try_get::<Json<_>, _>("body_multi")?.0),
-      "none" | _ => Self::None
-    })
-  }
-}
-
-impl TryFrom<Option<&YamlValue>> for Body {
-  type Error = Error;
-
-  fn try_from(value: Option<&YamlValue>) -> Result<Self, Self::Error> {
-    Ok(if let Some(body) = value {
-      match YamlParser::to_str_option(body.get("type"))? {
-        Some("text") => Self::Text(YamlParser::to_string_req(body, "text")?),
-        Some("json") => Self::Json(JsonValue::from_str(YamlParser::to_str(&YamlParser::get_req(body, "json")?)?)?),
-        Some("form") => Self::Form(YamlParser::to_hashmap_option(body.get("form"))?.unwrap_or_default()),
-        Some("multi") => Self::Multi(YamlParser::to_hashmap_option(body.get("form"))?.unwrap_or_default()),
-        Some("none") | None => Self::None,
-        Some(body_type) => Err(Error::String(format!("Souce body type `{}` invalid.", body_type)))?,
-      }
-    } else {
-      Self::None
+      "passthrough" => Self::Passthrough,
+      _ => Self::None
     })
   }
 }
\ No newline at end of file
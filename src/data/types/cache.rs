@@ -0,0 +1,52 @@
+use std::time::{Duration, SystemTime};
+use reqwest::header::{HeaderMap, CACHE_CONTROL, EXPIRES};
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cache {
+  pub ttl: Duration,
+  pub max_entries: usize,
+  pub max_bytes: Option<usize>,
+  pub cache_control: bool,
+  pub stale_while_revalidate: Option<Duration>,
+  pub stale_if_error: Option<Duration>,
+}
+
+impl Cache {
+  /// How long an expired entry is kept around to be served stale.
+  pub fn stale_window(&self) -> Duration {
+    self.stale_while_revalidate.unwrap_or_default().max(self.stale_if_error.unwrap_or_default())
+  }
+
+  /// How long a response may be cached, or `None` if it must not be. With `cache_control`
+  /// enabled the upstream `Cache-Control` and `Expires` headers win over the fixed `ttl`.
+  pub fn expiry(&self, headers: &HeaderMap) -> Option<Duration> {
+    if !self.cache_control {
+      return Some(self.ttl);
+    }
+
+    if let Some(cache_control) = headers.get(CACHE_CONTROL).and_then(|value| value.to_str().ok()) {
+      let directives = cache_control.split(',')
+        .map(|directive| directive.trim().to_lowercase())
+        .collect::<Vec<_>>();
+
+      if directives.iter().any(|directive| matches!(directive.as_str(), "no-store" | "no-cache" | "private")) {
+        return None;
+      }
+
+      for name in ["s-maxage=", "max-age="] {
+        if let Some(seconds) = directives.iter()
+          .find_map(|directive| directive.strip_prefix(name)?.parse::<u64>().ok()) {
+          return Some(Duration::from_secs(seconds)).filter(|ttl| !ttl.is_zero());
+        }
+      }
+    }
+
+    if let Some(expires) = headers.get(EXPIRES).and_then(|value| value.to_str().ok()) {
+      return httpdate::parse_http_date(expires).ok()?
+        .duration_since(SystemTime::now()).ok();
+    }
+
+    Some(self.ttl)
+  }
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CircuitBreaker {
+  pub failure_threshold: u32,
+  pub open_duration: Duration,
+  pub half_open_probes: u32,
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+/// Inbound query keys and headers passed on to a source, keyed by inbound name.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Forward {
+  pub query: HashMap<String, String>,
+  pub headers: HashMap<String, String>,
+}
\ No newline at end of file
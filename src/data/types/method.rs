@@ -0,0 +1,98 @@
+use std::fmt::{self, Display, Formatter};
+use serde::{Serialize, Deserialize};
+use sqlx::{
+  encode::IsNull,
+  error::BoxDynError,
+  postgres::{PgHasArrayType, PgRow, PgTypeInfo, PgValueRef},
+  Database, Decode, Encode,
+  FromRow, Postgres,
+  Row, Type
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Method {
+  #[default]
+  Get,
+  Post,
+  Put,
+  Patch,
+  Delete,
+  Head,
+  Options,
+}
+
+impl Method {
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name.to_lowercase().as_str() {
+      "get" => Self::Get,
+      "post" => Self::Post,
+      "put" => Self::Put,
+      "patch" => Self::Patch,
+      "delete" => Self::Delete,
+      "head" => Self::Head,
+      "options" => Self::Options,
+      _ => return None,
+    })
+  }
+}
+
+impl Display for Method {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Get => "get",
+      Self::Post => "post",
+      Self::Put => "put",
+      Self::Patch => "patch",
+      Self::Delete => "delete",
+      Self::Head => "head",
+      Self::Options => "options",
+    })
+  }
+}
+
+impl From<Method> for reqwest::Method {
+  fn from(value: Method) -> Self {
+    match value {
+      Method::Get => Self::GET,
+      Method::Post => Self::POST,
+      Method::Put => Self::PUT,
+      Method::Patch => Self::PATCH,
+      Method::Delete => Self::DELETE,
+      Method::Head => Self::HEAD,
+      Method::Options => Self::OPTIONS,
+    }
+  }
+}
+
+impl Type<Postgres> for Method {
+  fn type_info() -> PgTypeInfo {
+    PgTypeInfo::with_name("method")
+  }
+}
+
+impl PgHasArrayType for Method {
+  fn array_type_info() -> PgTypeInfo {
+    PgTypeInfo::with_name("_method")
+  }
+}
+
+impl Encode<'_, Postgres> for Method {
+  fn encode_by_ref(&self, buf: &mut <Postgres as Database>::ArgumentBuffer<'_>) -> Result<IsNull, BoxDynError> {
+    buf.extend(self.to_string().as_bytes());
+
+    Ok(IsNull::No)
+  }
+}
+
+impl Decode<'_, Postgres> for Method {
+  fn decode(value: PgValueRef<'_>) -> Result<Self, BoxDynError> {
+    let name = <&str as Decode<Postgres>>::decode(value)?;
+    Ok(Self::from_name(name).ok_or(format!("Method `{}` invalid.", name))?)
+  }
+}
+
+impl FromRow<'_, PgRow> for Method {
+  fn from_row(row: &'_ PgRow) -> Result<Self, sqlx::Error> {
+    Ok(Self::from_name(row.try_get_unchecked("method")?).unwrap_or_default())
+  }
+}
\ No newline at end of file
@@ -1,5 +1,17 @@
 pub mod auth;
 pub mod body;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod forward;
+pub mod method;
+pub mod multi_value;
+pub mod retry;
 
 pub use self::auth::Auth;
-pub use self::body::Body;
\ No newline at end of file
+pub use self::body::Body;
+pub use self::cache::Cache;
+pub use self::circuit_breaker::CircuitBreaker;
+pub use self::forward::Forward;
+pub use self::method::Method;
+pub use self::multi_value::{ListFormat, MultiValue};
+pub use self::retry::Retry;
\ No newline at end of file
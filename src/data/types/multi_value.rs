@@ -0,0 +1,93 @@
+use std::{collections::HashMap, fmt::{self, Display, Formatter}};
+use serde::{Serialize, Deserialize};
+use sqlx::{
+  encode::IsNull,
+  error::BoxDynError,
+  postgres::{PgRow, PgTypeInfo},
+  Database, Encode,
+  FromRow, Postgres,
+  Row, Type
+};
+
+/// Param or header value, either one value or a list sent as several.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum MultiValue {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl MultiValue {
+  pub fn values(&self) -> &[String] {
+    match self {
+      Self::One(value) => std::slice::from_ref(value),
+      Self::Many(values) => values,
+    }
+  }
+
+  pub fn try_map<E>(&self, f: impl Fn(&str) -> Result<String, E>) -> Result<Self, E> {
+    Ok(match self {
+      Self::One(value) => Self::One(f(value)?),
+      Self::Many(values) => Self::Many(values.iter().map(|value| f(value)).collect::<Result<_, _>>()?),
+    })
+  }
+}
+
+/// How a source sends list params: `ids=1&ids=2` or `ids=1,2`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+  #[default]
+  Repeat,
+  Comma,
+}
+
+impl ListFormat {
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "repeat" => Self::Repeat,
+      "comma" => Self::Comma,
+      _ => return None,
+    })
+  }
+
+  /// Query pairs for `params`, with lists repeated or comma joined.
+  pub fn pairs(&self, params: &HashMap<String, MultiValue>) -> Vec<(String, String)> {
+    params.iter()
+      .flat_map(|(key, value)| match (self, value) {
+        (Self::Comma, MultiValue::Many(values)) => vec![(key.to_owned(), values.join(","))],
+        (_, value) => value.values().iter()
+          .map(|value| (key.to_owned(), value.to_owned()))
+          .collect(),
+      })
+      .collect()
+  }
+}
+
+impl Display for ListFormat {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Repeat => "repeat",
+      Self::Comma => "comma",
+    })
+  }
+}
+
+impl Type<Postgres> for ListFormat {
+  fn type_info() -> PgTypeInfo {
+    PgTypeInfo::with_name("list_format")
+  }
+}
+
+impl Encode<'_, Postgres> for ListFormat {
+  fn encode_by_ref(&self, buf: &mut <Postgres as Database>::ArgumentBuffer<'_>) -> Result<IsNull, BoxDynError> {
+    buf.extend(self.to_string().as_bytes());
+
+    Ok(IsNull::No)
+  }
+}
+
+impl FromRow<'_, PgRow> for ListFormat {
+  fn from_row(row: &'_ PgRow) -> Result<Self, sqlx::Error> {
+    Ok(Self::from_name(row.try_get_unchecked("list_format")?).unwrap_or_default())
+  }
+}
\ No newline at end of file
@@ -0,0 +1,78 @@
+use std::{cmp, time::{Duration, SystemTime}};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryError {
+  Timeout,
+  Connect,
+  Request,
+  Body,
+}
+
+impl RetryError {
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "timeout" => Self::Timeout,
+      "connect" => Self::Connect,
+      "request" => Self::Request,
+      "body" => Self::Body,
+      _ => return None,
+    })
+  }
+
+  fn matches(&self, error: &reqwest::Error) -> bool {
+    match self {
+      Self::Timeout => error.is_timeout(),
+      Self::Connect => error.is_connect(),
+      Self::Request => error.is_request(),
+      Self::Body => error.is_body() || error.is_decode(),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Retry {
+  pub max_attempts: u32,
+  pub base_backoff: Duration,
+  pub max_backoff: Duration,
+  pub jitter: bool,
+  pub statuses: Vec<u16>,
+  pub errors: Vec<RetryError>,
+  pub retry_after: bool,
+}
+
+impl Retry {
+  pub fn is_retryable(&self, error: &reqwest::Error) -> bool {
+    self.errors.iter().any(|kind| kind.matches(error))
+  }
+
+  /// Delay before the attempt following `attempt`. A `Retry-After` value takes
+  /// precedence over the exponential backoff, but neither exceeds `max_backoff`.
+  pub fn backoff(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+    if let Some(retry_after) = headers.filter(|_| self.retry_after).and_then(Self::parse_retry_after) {
+      return cmp::min(retry_after, self.max_backoff);
+    }
+
+    let backoff = cmp::min(
+      self.base_backoff.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))),
+      self.max_backoff);
+
+    if self.jitter && !backoff.is_zero() {
+      rand::thread_rng().gen_range(Duration::ZERO..=backoff)
+    } else {
+      backoff
+    }
+  }
+
+  fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+
+    match value.trim().parse::<u64>() {
+      Ok(seconds) => Some(Duration::from_secs(seconds)),
+      Err(_) => httpdate::parse_http_date(value).ok()?
+        .duration_since(SystemTime::now()).ok(),
+    }
+  }
+}
\ No newline at end of file
@@ -1,9 +1,9 @@
 use api::FusionConfig;
 use dotenv::dotenv;
 use axum::{
-  routing::get, Router
+  routing::any, Router
 };
-use std::error::Error;
+use std::{env, error::Error};
 use tokio::task;
 
 pub mod api;
@@ -15,13 +15,40 @@ pub mod utils;
 async fn main() -> Result<(), Box<dyn Error>> {
   dotenv()?;
   data::init_pool().await?;
+
+  if config::run_command(&env::args().skip(1).collect::<Vec<_>>()).await? {
+    return Ok(());
+  }
+
   config::parse_config().await?;
+  api::RoutingTable::reload().await?;
+
+  task::spawn(async {
+    let result = config::watch(|| async {
+      config::parse_config().await?;
+      api::RoutingTable::reload().await?;
+      Ok(())
+    }).await;
+
+    if let Err(error) = result {
+      println!("Configuration watcher stopped: {}", error);
+    }
+  });
 
   let fusion_server = task::spawn(async move {
     let fusion_config = FusionConfig::env();
 
+    let body_limit = fusion_config.body_limit;
     let fusion_router = Router::new()
-      .route(&format!("{}/*path", &fusion_config.path), get(api::entrypoint));
+      .route(&format!("{}/*path", &fusion_config.path), any(move |request| api::entrypoint(request, body_limit)));
+    let fusion_router = match (&fusion_config.admin_path, &fusion_config.admin_token) {
+      (Some(admin_path), Some(admin_token)) => fusion_router.nest(admin_path, api::admin::router(admin_token.clone())),
+      (Some(_), None) => {
+        println!("API_ADMIN_TOKEN is not set, admin routes are disabled.");
+        fusion_router
+      },
+      (None, _) => fusion_router,
+    };
     let fusion_listener = tokio::net::TcpListener::bind(fusion_config.full_address()).await.unwrap();
     
     println!("Fusion server running on http://{}:{}{}", &fusion_config.address, &fusion_config.port, &fusion_config.path);
@@ -8,4 +8,14 @@ impl Hasher {
     hasher.update(value);
     hasher.finalize().to_vec()
   }
+
+  pub fn to_hex(value: &[u8]) -> String {
+    value.iter().map(|byte| format!("{:02x}", byte)).collect()
+  }
+
+  pub fn from_hex(value: &str) -> Option<Vec<u8>> {
+    (0..value.len()).step_by(2)
+      .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+      .collect()
+  }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
 pub mod hasher;
+pub mod path_pattern;
 
-pub use hasher::Hasher;
\ No newline at end of file
+pub use hasher::Hasher;
+pub use path_pattern::PathPattern;
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+enum Segment {
+  Static(String),
+  Param(String),
+}
+
+pub struct PathPattern {
+  segments: Vec<Segment>,
+}
+
+impl PathPattern {
+  pub fn new(pattern: &str) -> Self {
+    Self {
+      segments: Self::split(pattern)
+        .map(|segment| match segment.strip_prefix('{').and_then(|val| val.strip_suffix('}')) {
+          Some(name) => Segment::Param(name.to_owned()),
+          None => Segment::Static(segment.to_owned()),
+        })
+        .collect(),
+    }
+  }
+
+  pub fn captures(&self, path: &str) -> Option<HashMap<String, String>> {
+    let parts = Self::split(path).collect::<Vec<_>>();
+
+    if parts.len() != self.segments.len() {
+      return None;
+    }
+
+    let mut captures = HashMap::new();
+    for (segment, part) in self.segments.iter().zip(parts) {
+      match segment {
+        Segment::Static(value) if value == part => (),
+        Segment::Static(_) => return None,
+        Segment::Param(name) => { captures.insert(name.to_owned(), part.to_owned()); },
+      }
+    }
+
+    Some(captures)
+  }
+
+  /// Static segments outrank parameters, compared from left to right.
+  pub fn specificity(&self) -> Vec<bool> {
+    self.segments.iter().map(|segment| matches!(segment, Segment::Static(_))).collect()
+  }
+
+  fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn captures_params_by_name() {
+    let pattern = PathPattern::new("/users/{id}/orders/{order}");
+
+    let captures = pattern.captures("/users/42/orders/7").unwrap();
+    assert_eq!(captures.len(), 2);
+    assert_eq!(captures["id"], "42");
+    assert_eq!(captures["order"], "7");
+  }
+
+  #[test]
+  fn ignores_empty_segments() {
+    let pattern = PathPattern::new("/users/{id}/");
+
+    assert!(pattern.captures("users/42").is_some());
+    assert!(pattern.captures("//users//42/").is_some());
+  }
+
+  #[test]
+  fn rejects_mismatched_paths() {
+    let pattern = PathPattern::new("/users/{id}");
+
+    assert!(pattern.captures("/accounts/42").is_none());
+    assert!(pattern.captures("/users").is_none());
+    assert!(pattern.captures("/users/42/orders").is_none());
+  }
+
+  #[test]
+  fn static_segments_outrank_params_from_the_left() {
+    let exact = PathPattern::new("/users/me");
+    let param = PathPattern::new("/users/{id}");
+    let leading = PathPattern::new("/{scope}/me");
+
+    assert!(exact.specificity() > param.specificity());
+    assert!(param.specificity() > leading.specificity());
+  }
+}
\ No newline at end of file